@@ -0,0 +1,251 @@
+use super::config_manager::{add_seconds, ConfigError, ConfigurationManager, Updatable};
+use scrypto::prelude::rust::hash::Hash;
+use scrypto::prelude::*;
+use utils::InstantUtils;
+
+/// Errors returned by the approval manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalError<K> {
+  /// The quorum is zero or greater than the number of approvers
+  InvalidQuorum(u8),
+
+  /// The global id is not one of the approvers
+  NotAnApprover(NonFungibleGlobalId),
+
+  /// No proposal exists for the id
+  ProposalNotFound(u64),
+
+  /// The proposal cannot be approved or executed anymore
+  ProposalExpired(u64),
+
+  /// The approver already approved the proposal
+  AlreadyApproved(u64),
+
+  /// Only the proposer can cancel a proposal which is not expired
+  NotTheProposer(u64),
+
+  /// The proposed update was rejected by the configuration manager
+  Config(ConfigError<K>),
+}
+
+impl<K: Debug> fmt::Display for ApprovalError<K> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ApprovalError::InvalidQuorum(quorum) => write!(f, "Invalid approval quorum: {}", quorum),
+      ApprovalError::NotAnApprover(id) => write!(f, "{:?} is not an approver", id),
+      ApprovalError::ProposalNotFound(id) => write!(f, "Proposal {} not found", id),
+      ApprovalError::ProposalExpired(id) => write!(f, "Proposal {} is expired", id),
+      ApprovalError::AlreadyApproved(id) => write!(f, "Proposal {} is already approved by this approver", id),
+      ApprovalError::NotTheProposer(id) => write!(f, "Proposal {} can only be cancelled by its proposer", id),
+      ApprovalError::Config(err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl<K: Debug> std::error::Error for ApprovalError<K> {}
+
+impl<K> From<ConfigError<K>> for ApprovalError<K> {
+  fn from(err: ConfigError<K>) -> Self {
+    ApprovalError::Config(err)
+  }
+}
+
+/// A configuration update waiting for the approval quorum to be reached
+#[derive(ScryptoSbor, Clone)]
+pub struct Proposal<K: ScryptoSbor, U: ScryptoSbor> {
+  pub key: K,
+  pub update_inputs: U,
+  pub proposer: NonFungibleGlobalId,
+  pub approvals: IndexSet<NonFungibleGlobalId>,
+  pub expiration_time: Option<Instant>,
+}
+
+/// M-of-N approval of configuration updates. Approvers are identified by their badge and must
+/// present a proof of it in the auth zone when proposing or approving an update
+#[derive(ScryptoSbor)]
+pub struct ApprovalManager<K: ScryptoSbor + Hash + Eq + Copy + Debug, U: ScryptoSbor + Clone> {
+  // Config
+  approvers: IndexSet<NonFungibleGlobalId>,
+  quorum: u8,
+  proposal_expiration_time: Option<u64>,
+  // State
+  proposal_count: u64,
+  proposals: KeyValueStore<u64, Proposal<K, U>>,
+}
+
+impl<K: ScryptoSbor + Hash + Eq + Copy + Debug, U: ScryptoSbor + Clone> ApprovalManager<K, U> {
+  pub fn new(
+    approvers: IndexSet<NonFungibleGlobalId>,
+    quorum: u8,
+    proposal_expiration_time: Option<u64>,
+    proposals: KeyValueStore<u64, Proposal<K, U>>,
+  ) -> Result<Self, ApprovalError<K>> {
+    check_quorum(&approvers, quorum)?;
+
+    Ok(Self {
+      approvers,
+      quorum,
+      proposal_expiration_time,
+      proposal_count: 0,
+      proposals,
+    })
+  }
+
+  /// Replace the approvers and the quorum. Approvals given by removed approvers no longer count.
+  /// Proposals reaching the new quorum with their recorded approvals are applied by `execute_proposal`
+  pub fn update_approvers(&mut self, approvers: IndexSet<NonFungibleGlobalId>, quorum: u8) -> Result<(), ApprovalError<K>> {
+    check_quorum(&approvers, quorum)?;
+
+    self.approvers = approvers;
+    self.quorum = quorum;
+
+    Ok(())
+  }
+
+  pub fn update_proposal_expiration(&mut self, new_proposal_expiration_time: Option<u64>) {
+    self.proposal_expiration_time = new_proposal_expiration_time;
+  }
+
+  /// Submit an update of the entry of `key`, approved by its proposer. The update is validated
+  /// against the current entry and applied right away if the quorum is one
+  pub fn propose<C: ScryptoSbor + Clone + Updatable<U>>(
+    &mut self,
+    proposer: NonFungibleGlobalId,
+    key: K,
+    update_inputs: U,
+    config_manager: &mut ConfigurationManager<K, C, U>,
+  ) -> Result<u64, ApprovalError<K>> {
+    self.assert_approver(&proposer)?;
+
+    config_manager.validate_update(key, update_inputs.clone())?;
+
+    let expiration_time = self.proposal_expiration_time.map(|exp| add_seconds(Instant::now(), exp)).transpose()?;
+
+    let proposal_id = self.proposal_count;
+    self.proposal_count += 1;
+
+    // The proposer is already checked, its approval is recorded without asserting its proof again
+    let proposal = Proposal {
+      key,
+      update_inputs,
+      proposer: proposer.clone(),
+      approvals: indexset!(proposer),
+      expiration_time,
+    };
+
+    self.apply_or_store(proposal_id, proposal, config_manager)?;
+
+    Ok(proposal_id)
+  }
+
+  /// Record the approval of a proposal and apply the update to the configuration manager once the
+  /// quorum is reached. Returns whether the update was applied
+  pub fn approve<C: ScryptoSbor + Clone + Updatable<U>>(
+    &mut self,
+    approver: NonFungibleGlobalId,
+    proposal_id: u64,
+    config_manager: &mut ConfigurationManager<K, C, U>,
+  ) -> Result<bool, ApprovalError<K>> {
+    self.assert_approver(&approver)?;
+
+    let mut proposal = self.get_live_proposal(proposal_id)?;
+
+    if !proposal.approvals.insert(approver) {
+      return Err(ApprovalError::AlreadyApproved(proposal_id));
+    }
+
+    self.apply_or_store(proposal_id, proposal, config_manager)
+  }
+
+  /// Apply the update of a proposal whose recorded approvals reach the quorum, e.g. after the quorum
+  /// was lowered. No approval is added, so anyone can call it. Returns whether the update was applied
+  pub fn execute_proposal<C: ScryptoSbor + Clone + Updatable<U>>(
+    &mut self,
+    proposal_id: u64,
+    config_manager: &mut ConfigurationManager<K, C, U>,
+  ) -> Result<bool, ApprovalError<K>> {
+    let proposal = self.get_live_proposal(proposal_id)?;
+
+    self.apply_or_store(proposal_id, proposal, config_manager)
+  }
+
+  /// Drop a proposal. Only its proposer can cancel it before it expires
+  pub fn cancel_proposal(&mut self, caller: NonFungibleGlobalId, proposal_id: u64) -> Result<(), ApprovalError<K>> {
+    let proposal = self.get_proposal(proposal_id)?;
+
+    let is_expired = proposal.expiration_time.is_some_and(|time| time <= Instant::now());
+
+    if !is_expired {
+      if proposal.proposer != caller {
+        return Err(ApprovalError::NotTheProposer(proposal_id));
+      }
+
+      Runtime::assert_access_rule(rule!(require(caller)));
+    }
+
+    self.proposals.remove(&proposal_id);
+
+    Ok(())
+  }
+
+  pub fn get_proposal(&self, proposal_id: u64) -> Result<Proposal<K, U>, ApprovalError<K>> {
+    self
+      .proposals
+      .get(&proposal_id)
+      .map(|e| e.clone())
+      .ok_or(ApprovalError::ProposalNotFound(proposal_id))
+  }
+
+  /// Get a proposal which can still be approved or executed
+  fn get_live_proposal(&self, proposal_id: u64) -> Result<Proposal<K, U>, ApprovalError<K>> {
+    let proposal = self.get_proposal(proposal_id)?;
+
+    if proposal.expiration_time.is_some_and(|time| time <= Instant::now()) {
+      return Err(ApprovalError::ProposalExpired(proposal_id));
+    }
+
+    Ok(proposal)
+  }
+
+  /// Apply the update of the proposal once its approvals reach the quorum, store the proposal with
+  /// its approvals otherwise. Returns whether the update was applied
+  fn apply_or_store<C: ScryptoSbor + Clone + Updatable<U>>(
+    &mut self,
+    proposal_id: u64,
+    proposal: Proposal<K, U>,
+    config_manager: &mut ConfigurationManager<K, C, U>,
+  ) -> Result<bool, ApprovalError<K>> {
+    let approval_count = proposal.approvals.iter().filter(|id| self.approvers.contains(*id)).count();
+
+    if approval_count < self.quorum as usize {
+      self.proposals.insert(proposal_id, proposal);
+      return Ok(false);
+    }
+
+    // The proposal is only dropped once the update is applied, a rejected update leaves it as is
+    config_manager.update_entry(proposal.key, proposal.update_inputs)?;
+
+    self.proposals.remove(&proposal_id);
+
+    Ok(true)
+  }
+
+  /// Check that the id is an approver and that its proof is present in the auth zone
+  fn assert_approver(&self, id: &NonFungibleGlobalId) -> Result<(), ApprovalError<K>> {
+    if !self.approvers.contains(id) {
+      return Err(ApprovalError::NotAnApprover(id.clone()));
+    }
+
+    Runtime::assert_access_rule(rule!(require(id.clone())));
+
+    Ok(())
+  }
+}
+
+fn check_quorum<K>(approvers: &IndexSet<NonFungibleGlobalId>, quorum: u8) -> Result<(), ApprovalError<K>> {
+  if quorum == 0 || quorum as usize > approvers.len() {
+    return Err(ApprovalError::InvalidQuorum(quorum));
+  }
+
+  Ok(())
+}
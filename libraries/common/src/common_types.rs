@@ -8,6 +8,58 @@ pub enum UpdateSetInput<T> {
   Remove(T),
 }
 
+/// Define an update option
+#[derive(ScryptoSbor, ManifestSbor, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UpdateOptionInput<T> {
+  Set(T),
+  Clear,
+}
+
+/// Define an update vec, indexes out of range are rejected
+#[derive(ScryptoSbor, ManifestSbor, Debug, Clone, PartialEq, Eq, Hash)]
+#[sbor(categorize_types = "T")]
+pub enum UpdateVecInput<T> {
+  Push(T),
+  RemoveAt(u32),
+  Replace(u32, T),
+  Clear,
+  Set(Vec<T>),
+}
+
+/// Define the previous value of a vec field in an update report
+#[derive(ScryptoSbor, Debug, Clone, PartialEq)]
+#[sbor(categorize_types = "T")]
+pub enum PreviousVecValue<T> {
+  /// Nothing is replaced by a `Push`
+  Nothing,
+
+  /// The element at the index of a `RemoveAt` or a `Replace`
+  Element(T),
+
+  /// The whole vec replaced by a `Clear` or a `Set`
+  All(Vec<T>),
+}
+
+/// Define the change made by an update input, with the previous value of the updated field
+#[derive(ScryptoSbor, Debug, Clone, PartialEq)]
+pub struct UpdateChange<I, V> {
+  pub input: I,
+  pub previous: V,
+  pub is_no_op: bool,
+}
+
+/// Define the report of an update, listing the changes in the order they were applied
+#[derive(ScryptoSbor, Debug, Clone, PartialEq)]
+pub struct UpdateReport<I, V> {
+  pub changes: Vec<UpdateChange<I, V>>,
+}
+impl<I, V> UpdateReport<I, V> {
+  /// Returns true if none of the inputs changed the updated item
+  pub fn is_no_op(&self) -> bool {
+    self.changes.iter().all(|change| change.is_no_op)
+  }
+}
+
 /// Define an empty badge
 #[derive(ScryptoSbor, NonFungibleData)]
 pub struct EmptyBadgeData {}
@@ -1,96 +1,671 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use scrypto::prelude::rust::hash::Hash;
 use scrypto::prelude::*;
 use utils::InstantUtils;
 
+/// Errors returned by the configuration manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError<K> {
+  /// No current entry exists for the key
+  KeyNotFound(K),
+
+  /// No history entry was ever recorded under the version
+  VersionNotFound(u64),
+
+  /// The history entry recorded under the version has been pruned after its expiration
+  VersionPruned(u64),
+
+  /// The history entry recorded under the version belongs to another key
+  VersionKeyMismatch(u64),
+
+  /// The entry was rejected by `Updatable::update` or `Updatable::check`
+  ValidationFailed(String),
+
+  /// An entry already exists for the key
+  AlreadyExists(K),
+
+  /// The history entry recorded under the version is already expired
+  Expired(u64),
+
+  /// The history entry recorded under the version is the tombstone of a removed key
+  Removed(u64),
+
+  /// No pending update exists for the key
+  PendingUpdateNotFound(K),
+
+  /// A pending update already exists for the key
+  PendingUpdateAlreadyExists(K),
+
+  /// The pending update of the key cannot be executed before its activation time
+  PendingUpdateNotActive(K),
+
+  /// The duration in seconds cannot be added to the current time
+  InvalidDuration(u64),
+
+  /// The batch update holds no update
+  EmptyBatch,
+
+  /// No scheduled entry exists for the key
+  ScheduledEntryNotFound(K),
+
+  /// A scheduled entry already exists for the key
+  ScheduledEntryAlreadyExists(K),
+
+  /// The effective time of the scheduled entry of the key is not in the future
+  ScheduledEntryNotInFuture(K),
+
+  /// The keys of the current entries are not tracked
+  KeyRegistryDisabled,
+
+  /// The history of the entries is not tracked
+  HistoryDisabled,
+
+  /// A retention policy other than `KeepAll` is set without a default expiration time, versions
+  /// without expiration are never pruned
+  RetentionWithoutExpiration,
+}
+
+impl<K: Debug> fmt::Display for ConfigError<K> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConfigError::KeyNotFound(key) => write!(f, "Configuration not found for key {:?}", key),
+      ConfigError::VersionNotFound(version) => write!(f, "Configuration version {} not found", version),
+      ConfigError::VersionPruned(version) => write!(f, "Configuration version {} has been pruned", version),
+      ConfigError::VersionKeyMismatch(version) => write!(f, "Configuration version {} belongs to another key", version),
+      ConfigError::ValidationFailed(reason) => write!(f, "Configuration validation failed: {}", reason),
+      ConfigError::AlreadyExists(key) => write!(f, "Configuration already exists for key {:?}", key),
+      ConfigError::Expired(version) => write!(f, "Configuration version {} is expired", version),
+      ConfigError::Removed(version) => write!(f, "Configuration version {} records a removed key", version),
+      ConfigError::PendingUpdateNotFound(key) => write!(f, "No pending configuration update for key {:?}", key),
+      ConfigError::PendingUpdateAlreadyExists(key) => write!(f, "A pending configuration update already exists for key {:?}", key),
+      ConfigError::PendingUpdateNotActive(key) => write!(f, "Pending configuration update for key {:?} is not active yet", key),
+      ConfigError::InvalidDuration(seconds) => write!(f, "Invalid configuration duration: {} seconds", seconds),
+      ConfigError::EmptyBatch => write!(f, "Configuration batch update is empty"),
+      ConfigError::ScheduledEntryNotFound(key) => write!(f, "No scheduled configuration for key {:?}", key),
+      ConfigError::ScheduledEntryAlreadyExists(key) => write!(f, "A scheduled configuration already exists for key {:?}", key),
+      ConfigError::ScheduledEntryNotInFuture(key) => write!(f, "Scheduled configuration for key {:?} must take effect in the future", key),
+      ConfigError::KeyRegistryDisabled => write!(f, "Configuration keys are not tracked"),
+      ConfigError::HistoryDisabled => write!(f, "Configuration history is not tracked"),
+      ConfigError::RetentionWithoutExpiration => write!(f, "Configuration history retention requires a default expiration time"),
+    }
+  }
+}
+
+impl<K: Debug> std::error::Error for ConfigError<K> {}
+
+/// The current entry of a key. It is stored apart from the history so that reading it only loads
+/// the configuration and its version. `scheduled_at` mirrors the effective time of the scheduled
+/// entry of the key, so that the scheduled entries are only read once one is due. `key_position`
+/// is the position of the key in the key registry
 #[derive(ScryptoSbor, Clone)]
-pub enum ConfigurationKey<K: ScryptoSbor + Hash + Copy> {
-  Current(K),
-  History(u64),
+pub struct CurrentEntry<C: ScryptoSbor> {
+  entry: C,
+  version: u64,
+  scheduled_at: Option<Instant>,
+  key_position: u64,
 }
 
+/// A version of an entry recorded in the history
 #[derive(ScryptoSbor, Clone)]
 pub struct ConfigurationEntry<K: ScryptoSbor + Hash + Copy, C: ScryptoSbor> {
   key: K,
   entry: C,
   version: u64,
   expiration_time: Option<Instant>,
+  timestamp: Instant,
+  removed: bool,
+  changeset: Option<u64>,
+}
+
+/// Define which history versions can be pruned. The current version of an entry is always kept, and
+/// a version is only pruned once its expiration time has passed, so versions without expiration
+/// are never pruned and newer versions are pruned around them. A policy other than `KeepAll`
+/// requires a default expiration time
+#[derive(ScryptoSbor, ManifestSbor, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryRetentionPolicy {
+  /// Keep every version
+  KeepAll,
+
+  /// Keep the last N versions of each entry
+  KeepLast(u64),
+
+  /// Keep the versions recorded less than N seconds ago
+  MaxAge(u64),
+}
+
+/// Positions of the oldest and of the next history version of a key in the version index, and
+/// position from which `prune_history` resumes. Versions pruned after a version still in use leave
+/// gaps in the positions
+#[derive(ScryptoSbor, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VersionRange {
+  first: u64,
+  next: u64,
+  cursor: u64,
+}
+
+/// Stores holding the history of the entries. The versions of each key are indexed by position in
+/// `versions`, `version_ranges` holding the range of positions in use for the key
+#[derive(ScryptoSbor)]
+pub struct HistoryStores<K: ScryptoSbor + Hash + Copy, C: ScryptoSbor> {
+  pub entries: KeyValueStore<u64, ConfigurationEntry<K, C>>,
+  pub versions: KeyValueStore<(K, u64), u64>,
+  pub version_ranges: KeyValueStore<K, VersionRange>,
+}
+
+/// Stores used by the configuration manager. The keys of the current entries are only tracked
+/// when `keys` is set, and the history of the entries only when `history` is set
+pub struct ConfigurationStores<K: ScryptoSbor + Hash + Copy, C: ScryptoSbor, U: ScryptoSbor> {
+  pub current_entries: KeyValueStore<K, CurrentEntry<C>>,
+  pub pending_updates: KeyValueStore<K, PendingUpdate<U>>,
+  pub scheduled_entries: KeyValueStore<K, ScheduledEntry<C>>,
+  pub keys: Option<KeyValueStore<u64, K>>,
+  pub history: Option<HistoryStores<K, C>>,
+}
+
+/// Maximum number of history versions visited by `prune_history` each time a version is recorded
+const MAX_AUTO_PRUNED_VERSIONS: u64 = 5;
+
+/// An update waiting for its timelock to elapse before it can be applied
+#[derive(ScryptoSbor, Clone)]
+pub struct PendingUpdate<U: ScryptoSbor> {
+  pub update_inputs: U,
+  pub activation_time: Instant,
+}
+
+/// An entry that replaces the current one once its effective time is reached. Its version is
+/// reserved and recorded in the history when it is scheduled
+#[derive(ScryptoSbor, Clone)]
+pub struct ScheduledEntry<C: ScryptoSbor> {
+  pub entry: C,
+  pub effective_at: Instant,
+  pub version: u64,
+  // Position of the version in the version index of the key, dropped with the scheduled entry
+  position: u64,
+}
+
+/// Emitted when an entry is created for a new key
+#[derive(ScryptoSbor)]
+pub struct ConfigEntryCreated<K: ScryptoSbor> {
+  pub key: K,
+  pub version: u64,
+}
+
+/// Emitted when the current entry of a key is replaced. `update_inputs` is not set when the new
+/// entry comes from a rollback or a scheduled entry, `changeset` is only set for batch updates
+#[derive(ScryptoSbor)]
+pub struct ConfigEntryUpdated<K: ScryptoSbor, U: ScryptoSbor> {
+  pub key: K,
+  pub old_version: u64,
+  pub new_version: u64,
+  pub update_inputs: Option<U>,
+  pub changeset: Option<u64>,
+}
+
+/// Emitted when the current entry of a key is removed. `version` is the one of the tombstone
+#[derive(ScryptoSbor)]
+pub struct ConfigEntryRemoved<K: ScryptoSbor> {
+  pub key: K,
+  pub version: u64,
+}
+
+/// Emitted when a history version is set as expired
+#[derive(ScryptoSbor)]
+pub struct ConfigVersionExpired<K: ScryptoSbor> {
+  pub key: K,
+  pub version: u64,
+}
+
+// `ScryptoEvent` cannot be derived for generic types
+impl<K: ScryptoSbor> ScryptoEvent for ConfigEntryCreated<K> {
+  const EVENT_NAME: &'static str = "ConfigEntryCreated";
+}
+
+impl<K: ScryptoSbor, U: ScryptoSbor> ScryptoEvent for ConfigEntryUpdated<K, U> {
+  const EVENT_NAME: &'static str = "ConfigEntryUpdated";
+}
+
+impl<K: ScryptoSbor> ScryptoEvent for ConfigEntryRemoved<K> {
+  const EVENT_NAME: &'static str = "ConfigEntryRemoved";
+}
+
+impl<K: ScryptoSbor> ScryptoEvent for ConfigVersionExpired<K> {
+  const EVENT_NAME: &'static str = "ConfigVersionExpired";
 }
 
 #[derive(ScryptoSbor)]
-pub struct ConfigurationManager<K: ScryptoSbor + Hash + Copy, C: ScryptoSbor + Clone + Updatable<U>, U: ScryptoSbor> {
+pub struct ConfigurationManager<K: ScryptoSbor + Hash + Eq + Copy + Debug, C: ScryptoSbor + Clone + Updatable<U>, U: ScryptoSbor + Clone> {
   // Config
-  track_history: bool,
-  get_config_error_message: String,
   default_expiration_time: Option<u64>,
+  history_retention: HistoryRetentionPolicy,
+  emit_events: bool,
   // State
   version_count: u64,
+  changeset_count: u64,
   entry_count: u16,
-  entries: KeyValueStore<ConfigurationKey<K>, ConfigurationEntry<K, C>>,
-  phantom_data: Option<U>,
+  keys: Option<KeyValueStore<u64, K>>,
+  current_entries: KeyValueStore<K, CurrentEntry<C>>,
+  history: Option<HistoryStores<K, C>>,
+  pending_updates: KeyValueStore<K, PendingUpdate<U>>,
+  scheduled_entries: KeyValueStore<K, ScheduledEntry<C>>,
 }
 
-impl<K: ScryptoSbor + Hash + Copy, C: ScryptoSbor + Clone + Updatable<U>, U: ScryptoSbor> ConfigurationManager<K, C, U> {
+impl<K: ScryptoSbor + Hash + Eq + Copy + Debug, C: ScryptoSbor + Clone + Updatable<U>, U: ScryptoSbor + Clone> ConfigurationManager<K, C, U> {
+  /// When `emit_events` is set, the `ConfigEntryCreated`, `ConfigEntryUpdated`, `ConfigEntryRemoved`
+  /// and `ConfigVersionExpired` events are emitted. Their `EVENT_NAME` is the same for every `K` and
+  /// `U`, so each concrete event type must be registered in the `#[events(...)]` of the blueprint,
+  /// otherwise `Runtime::emit_event` fails. A generic path is registered under a mangled name, e.g.
+  /// `ConfigEntryCreated_u16`, so register an alias named after the event instead:
+  /// `type ConfigEntryUpdated = common::config_manager::ConfigEntryUpdated<ResourceAddress, MyConfigInputs>;`
+  ///
+  /// Only expired versions are pruned, so a `history_retention` other than `KeepAll` requires a
+  /// `default_expiration_time`, otherwise `RetentionWithoutExpiration` is returned
   pub fn new(
-    track_history: bool,
     default_expiration_time: Option<u64>,
-    get_config_error_message: String,
-    entries: KeyValueStore<ConfigurationKey<K>, ConfigurationEntry<K, C>>,
-  ) -> Self {
-    Self {
-      track_history,
+    history_retention: HistoryRetentionPolicy,
+    emit_events: bool,
+    stores: ConfigurationStores<K, C, U>,
+  ) -> Result<Self, ConfigError<K>> {
+    check_retention(default_expiration_time, history_retention)?;
+
+    Ok(Self {
       default_expiration_time,
-      get_config_error_message,
-      entries,
+      history_retention,
+      emit_events,
+      current_entries: stores.current_entries,
+      history: stores.history,
+      pending_updates: stores.pending_updates,
+      scheduled_entries: stores.scheduled_entries,
       entry_count: 0,
+      keys: stores.keys,
       version_count: 0,
-      phantom_data: None,
-    }
+      changeset_count: 0,
+    })
   }
 
-  pub fn update_default_expiration(&mut self, new_default_expiration: Option<u64>) {
+  /// The default expiration can only be cleared while the retention policy is `KeepAll`
+  pub fn update_default_expiration(&mut self, new_default_expiration: Option<u64>) -> Result<(), ConfigError<K>> {
+    check_retention(new_default_expiration, self.history_retention)?;
+
     self.default_expiration_time = new_default_expiration;
+
+    Ok(())
+  }
+
+  /// A retention policy other than `KeepAll` can only be set with a default expiration
+  pub fn update_history_retention(&mut self, new_history_retention: HistoryRetentionPolicy) -> Result<(), ConfigError<K>> {
+    check_retention(self.default_expiration_time, new_history_retention)?;
+
+    self.history_retention = new_history_retention;
+
+    Ok(())
   }
 
   pub fn get_entry_count(&self) -> u16 {
     self.entry_count
   }
 
-  pub fn new_entry(&mut self, key: K, entry: C) -> Result<()> {
-    self.set_entry(key, entry, true)
+  pub fn new_entry(&mut self, key: K, entry: C) -> Result<(), ConfigError<K>> {
+    self.apply_scheduled_entry(key)?;
+
+    if self.current_entries.get(&key).is_some() {
+      return Err(ConfigError::AlreadyExists(key));
+    }
+
+    entry.check().map_err(|err| ConfigError::ValidationFailed(err.to_string()))?;
+
+    self.set_entry(key, entry, true, None, None)
   }
 
-  pub fn update_entry(&mut self, key: K, update_inputs: U) -> Result<()> {
+  /// Remove the current entry of `key` and record a tombstone version in the history.
+  /// Pending updates and scheduled entries of the key are dropped
+  pub fn remove_entry(&mut self, key: K) -> Result<(), ConfigError<K>> {
+    self.apply_scheduled_entry(key)?;
+
+    let current_entry = self.current_entries.remove(&key).ok_or(ConfigError::KeyNotFound(key))?;
+
+    self.pending_updates.remove(&key);
+    self.drop_scheduled_entry(key);
+
+    let version = self.record_history(key, current_entry.entry, true, None, Instant::now())?;
+
+    self.prune_history(key, MAX_AUTO_PRUNED_VERSIONS)?;
+
+    self.entry_count -= 1;
+
+    // The last key takes the position of the removed one so that the positions stay contiguous
+    if let Some(keys) = self.keys.as_ref() {
+      let last_position = self.entry_count as u64;
+
+      let last_key = keys.get(&last_position).map(|key| *key);
+
+      if let Some(last_key) = last_key.filter(|_| current_entry.key_position != last_position) {
+        keys.insert(current_entry.key_position, last_key);
+
+        if let Some(mut last_entry) = self.current_entries.get_mut(&last_key) {
+          last_entry.key_position = current_entry.key_position;
+        }
+      }
+
+      keys.remove(&last_position);
+    }
+
+    self.emit_event(ConfigEntryRemoved { key, version });
+
+    Ok(())
+  }
+
+  /// Check that the update inputs can be applied to the current entry of `key` without changing it
+  pub fn validate_update(&self, key: K, update_inputs: U) -> Result<(), ConfigError<K>> {
+    let mut current_entry = self.get_current_entry(key)?;
+
+    apply_update(&mut current_entry, update_inputs)
+  }
+
+  pub fn update_entry(&mut self, key: K, update_inputs: U) -> Result<(), ConfigError<K>> {
+    self.apply_scheduled_entry(key)?;
+
     let mut current_entry = self.get_current_entry(key)?;
 
-    current_entry.update(update_inputs)?;
-    current_entry.check()?;
+    apply_update(&mut current_entry, update_inputs.clone())?;
 
-    self.set_entry(key, current_entry, false)
+    self.set_entry(key, current_entry, false, Some(update_inputs), None)
   }
 
-  pub fn set_entry_expired(&mut self, version: u64) -> Result<()> {
-    if let Some(mut entry) = self.entries.get_mut(&ConfigurationKey::History(version)) {
-      entry.expiration_time = Some(Instant::now());
-      Ok(())
-    } else {
-      Err(anyhow!(self.get_config_error_message.clone()))
+  /// Update the entry of `key` and record the new version with its own expiration delay in seconds.
+  /// Unlike `update_entry`, which applies `default_expiration_time`, a `None` expiration time does not
+  /// fall back to the default: the new version never expires
+  pub fn update_entry_with_expiration(&mut self, key: K, update_inputs: U, expiration_time: Option<u64>) -> Result<(), ConfigError<K>> {
+    if self.history.is_none() {
+      return Err(ConfigError::HistoryDisabled);
     }
+
+    let expiration_time = expiration_time.map(|exp| add_seconds(Instant::now(), exp)).transpose()?;
+
+    self.update_entry(key, update_inputs)?;
+
+    let version = self.get_current_version(key)?;
+
+    self.get_history_record_mut(version)?.expiration_time = expiration_time;
+
+    Ok(())
   }
 
-  pub fn get_current_entry(&self, key: K) -> Result<C> {
+  /// Apply updates to several entries at once. Every update is validated before any of them is
+  /// applied and the resulting versions are recorded under a single changeset id, which is returned.
+  /// An empty batch is rejected so that every changeset id records at least one version
+  pub fn update_entries(&mut self, updates: Vec<(K, U)>) -> Result<u64, ConfigError<K>> {
+    if updates.is_empty() {
+      return Err(ConfigError::EmptyBatch);
+    }
+
+    let mut staged_entries: IndexMap<K, C> = IndexMap::new();
+    let mut staged_updates = Vec::with_capacity(updates.len());
+
+    for (key, update_inputs) in updates {
+      // A key updated several times in the batch is updated from its staged entry
+      let mut entry = match staged_entries.get(&key) {
+        Some(entry) => entry.clone(),
+        None => self.get_current_entry(key)?,
+      };
+
+      apply_update(&mut entry, update_inputs.clone())?;
+
+      staged_entries.insert(key, entry.clone());
+      staged_updates.push((key, entry, update_inputs));
+    }
+
+    let changeset = self.changeset_count;
+    self.changeset_count += 1;
+
+    for (key, entry, update_inputs) in staged_updates {
+      self.apply_scheduled_entry(key)?;
+      self.set_entry(key, entry, false, Some(update_inputs), Some(changeset))?;
+    }
+
+    Ok(changeset)
+  }
+
+  /// Validate an update against the current entry and store it until `delay_seconds` have elapsed
+  pub fn propose_update(&mut self, key: K, update_inputs: U, delay_seconds: u64) -> Result<Instant, ConfigError<K>> {
+    if self.pending_updates.get(&key).is_some() {
+      return Err(ConfigError::PendingUpdateAlreadyExists(key));
+    }
+
+    self.apply_scheduled_entry(key)?;
+
+    let mut current_entry = self.get_current_entry(key)?;
+
+    apply_update(&mut current_entry, update_inputs.clone())?;
+
+    let activation_time = add_seconds(Instant::now(), delay_seconds)?;
+
+    self.pending_updates.insert(
+      key,
+      PendingUpdate {
+        update_inputs,
+        activation_time,
+      },
+    );
+
+    Ok(activation_time)
+  }
+
+  /// Apply a pending update once its activation time is reached. The update is validated again
+  /// against the current entry as it may have changed since the proposal, a rejected update is kept
+  /// pending so that it can be cancelled or executed later
+  pub fn execute_pending(&mut self, key: K) -> Result<(), ConfigError<K>> {
+    let pending_update = self.get_pending_update(key)?;
+
+    if Instant::now() < pending_update.activation_time {
+      return Err(ConfigError::PendingUpdateNotActive(key));
+    }
+
+    self.update_entry(key, pending_update.update_inputs)?;
+
+    self.pending_updates.remove(&key);
+
+    Ok(())
+  }
+
+  pub fn cancel_pending(&mut self, key: K) -> Result<(), ConfigError<K>> {
     self
-      .entries
-      .get(&ConfigurationKey::Current(key))
-      .map(|e| e.entry.clone())
-      .ok_or_else(|| anyhow!(self.get_config_error_message.clone()))
+      .pending_updates
+      .remove(&key)
+      .map(|_| ())
+      .ok_or(ConfigError::PendingUpdateNotFound(key))
   }
 
-  pub fn get_history_entry(&self, version: u64) -> Result<(C, bool)> {
-    let entry = self
-      .entries
-      .get(&ConfigurationKey::History(version))
-      .ok_or_else(|| anyhow!(self.get_config_error_message.clone()))?;
+  pub fn get_pending_update(&self, key: K) -> Result<PendingUpdate<U>, ConfigError<K>> {
+    self
+      .pending_updates
+      .get(&key)
+      .map(|e| e.clone())
+      .ok_or(ConfigError::PendingUpdateNotFound(key))
+  }
+
+  /// Restore the configuration recorded under `version` as a new version of `key`
+  pub fn rollback_to_version(&mut self, key: K, version: u64) -> Result<(), ConfigError<K>> {
+    self.apply_scheduled_entry(key)?;
+
+    // The entry to roll back must still be live
+    self.get_current_version(key)?;
+
+    let history_entry = self.get_history_record(version)?.clone();
+
+    if history_entry.key != key {
+      return Err(ConfigError::VersionKeyMismatch(version));
+    }
+
+    if history_entry.removed {
+      return Err(ConfigError::Removed(version));
+    }
+
+    history_entry
+      .entry
+      .check()
+      .map_err(|err| ConfigError::ValidationFailed(err.to_string()))?;
+
+    self.set_entry(key, history_entry.entry, false, None, None)
+  }
+
+  /// Schedule `entry` to replace the current entry of `key` at `effective_at`. Only existing entries
+  /// can be scheduled, so that a due scheduled entry is always listed and counted with its key.
+  /// The version of the scheduled entry is recorded right away with `effective_at` as timestamp, so
+  /// an entry updated before the effective time gets a newer version than the scheduled one
+  pub fn schedule_entry(&mut self, key: K, entry: C, effective_at: Instant) -> Result<(), ConfigError<K>> {
+    self.apply_scheduled_entry(key)?;
+
+    if self.current_entries.get(&key).is_none() {
+      return Err(ConfigError::KeyNotFound(key));
+    }
+
+    if self.scheduled_entries.get(&key).is_some() {
+      return Err(ConfigError::ScheduledEntryAlreadyExists(key));
+    }
+
+    if effective_at <= Instant::now() {
+      return Err(ConfigError::ScheduledEntryNotInFuture(key));
+    }
+
+    entry.check().map_err(|err| ConfigError::ValidationFailed(err.to_string()))?;
+
+    let position = self.get_version_range(key).next;
+    let version = self.record_history(key, entry.clone(), false, None, effective_at)?;
+
+    self.scheduled_entries.insert(
+      key,
+      ScheduledEntry {
+        entry,
+        effective_at,
+        version,
+        position,
+      },
+    );
+
+    self.prune_history(key, MAX_AUTO_PRUNED_VERSIONS)?;
+
+    self.set_scheduled_at(key, Some(effective_at));
+
+    Ok(())
+  }
+
+  /// Drop the scheduled entry of `key` with the version recorded for it. A due scheduled entry may
+  /// already have been read as the current entry, it is applied instead and `ScheduledEntryNotFound`
+  /// is returned
+  pub fn cancel_scheduled_entry(&mut self, key: K) -> Result<(), ConfigError<K>> {
+    self.apply_scheduled_entry(key)?;
+
+    self.drop_scheduled_entry(key).ok_or(ConfigError::ScheduledEntryNotFound(key))?;
+
+    Ok(())
+  }
+
+  pub fn get_scheduled_entry(&self, key: K) -> Result<ScheduledEntry<C>, ConfigError<K>> {
+    self
+      .scheduled_entries
+      .get(&key)
+      .map(|e| e.clone())
+      .ok_or(ConfigError::ScheduledEntryNotFound(key))
+  }
+
+  /// Move the scheduled entry of `key` to the current entry once its effective time is reached, its
+  /// version is already recorded. Called by every method changing the entry, returns whether the
+  /// scheduled entry was applied
+  pub fn apply_scheduled_entry(&mut self, key: K) -> Result<bool, ConfigError<K>> {
+    let scheduled_at = self.current_entries.get(&key).and_then(|e| e.scheduled_at);
+
+    if !is_due(scheduled_at) {
+      return Ok(false);
+    }
+
+    let Some(scheduled_entry) = self.scheduled_entries.remove(&key) else {
+      return Ok(false);
+    };
+
+    let mut current_entry = self.current_entries.get_mut(&key).ok_or(ConfigError::KeyNotFound(key))?;
+
+    let old_version = current_entry.version;
+
+    current_entry.entry = scheduled_entry.entry;
+    current_entry.version = scheduled_entry.version;
+    current_entry.scheduled_at = None;
+
+    drop(current_entry);
+
+    self.emit_event(ConfigEntryUpdated {
+      key,
+      old_version,
+      new_version: scheduled_entry.version,
+      update_inputs: None::<U>,
+      changeset: None,
+    });
+
+    Ok(true)
+  }
+
+  /// Delay the expiration of a history version by `seconds`. An expired version cannot be revived.
+  /// A version without expiration already never expires, it is left unchanged and `Ok` is returned
+  pub fn extend_expiration(&mut self, version: u64, seconds: u64) -> Result<(), ConfigError<K>> {
+    let mut entry = self.get_history_record_mut(version)?;
+
+    if let Some(expiration_time) = entry.expiration_time {
+      if expiration_time <= Instant::now() {
+        return Err(ConfigError::Expired(version));
+      }
+
+      let new_expiration_time = add_seconds(expiration_time, seconds)?;
+
+      entry.expiration_time = Some(new_expiration_time);
+    }
+
+    Ok(())
+  }
+
+  pub fn set_entry_expired(&mut self, version: u64) -> Result<(), ConfigError<K>> {
+    let mut entry = self.get_history_record_mut(version)?;
+
+    let now = Instant::now();
+
+    if entry.expiration_time.is_some_and(|time| time <= now) {
+      return Err(ConfigError::Expired(version));
+    }
+
+    entry.expiration_time = Some(now);
+
+    let key = entry.key;
+    drop(entry);
+
+    self.emit_event(ConfigVersionExpired { key, version });
+
+    Ok(())
+  }
+
+  /// Get the current entry of `key`. A scheduled entry is returned as soon as its effective time
+  /// is reached, even if it was not applied yet
+  pub fn get_current_entry(&self, key: K) -> Result<C, ConfigError<K>> {
+    self.get_current_entry_with_version(key).map(|(entry, _)| entry)
+  }
+
+  /// Get the current entry of `key` with its version. A due scheduled entry is returned with the
+  /// version recorded when it was scheduled
+  pub fn get_current_entry_with_version(&self, key: K) -> Result<(C, u64), ConfigError<K>> {
+    let current_entry = self.current_entries.get(&key).ok_or(ConfigError::KeyNotFound(key))?;
+
+    if !is_due(current_entry.scheduled_at) {
+      return Ok((current_entry.entry.clone(), current_entry.version));
+    }
+
+    let scheduled_entry = self.scheduled_entries.get(&key).ok_or(ConfigError::ScheduledEntryNotFound(key))?;
+
+    Ok((scheduled_entry.entry.clone(), scheduled_entry.version))
+  }
+
+  pub fn get_history_entry(&self, version: u64) -> Result<(C, bool), ConfigError<K>> {
+    let entry = self.get_history_record(version)?;
+
+    if entry.removed {
+      return Err(ConfigError::Removed(version));
+    }
 
     let is_from_history = entry.expiration_time.map_or(true, |time| time > Instant::now());
 
@@ -103,46 +678,391 @@ impl<K: ScryptoSbor + Hash + Copy, C: ScryptoSbor + Clone + Updatable<U>, U: Scr
     Ok((returned_entry, is_from_history))
   }
 
-  pub fn get_current_version(&self, key: K) -> Result<u64> {
+  /// Get the version of the current entry of `key`, which is the one of a due scheduled entry even
+  /// before it is applied
+  pub fn get_current_version(&self, key: K) -> Result<u64, ConfigError<K>> {
+    self.get_current_entry_with_version(key).map(|(_, version)| version)
+  }
+
+  /// Get a page of the current entries with their version. Keys are listed in creation order until
+  /// one is removed, the last key then takes the position of the removed one. Only available when
+  /// the keys are tracked
+  pub fn get_all_current_entries(&self, offset: u64, limit: u64) -> Result<Vec<(K, u64, C)>, ConfigError<K>> {
+    let keys = self.keys.as_ref().ok_or(ConfigError::KeyRegistryDisabled)?;
+
+    let start = offset.min(self.entry_count as u64);
+    let end = start.saturating_add(limit).min(self.entry_count as u64);
+
+    (start..end)
+      .filter_map(|position| keys.get(&position).map(|key| *key))
+      .map(|key| {
+        let (entry, version) = self.get_current_entry_with_version(key)?;
+
+        Ok((key, version, entry))
+      })
+      .collect()
+  }
+
+  /// Get the id of the batch update which recorded `version`, if any
+  pub fn get_history_changeset(&self, version: u64) -> Result<Option<u64>, ConfigError<K>> {
+    self.get_history_record(version).map(|e| e.changeset)
+  }
+
+  /// Get a page of the history versions recorded for `key`, from the oldest to the newest. Pages are
+  /// taken over the positions of the versions, so a page holds less than `limit` versions when some
+  /// of them were pruned
+  pub fn get_versions_for_key(&self, key: K, offset: u64, limit: u64) -> Vec<u64> {
+    let range = self.get_version_range(key);
+
+    let start = range.first.saturating_add(offset).min(range.next);
+    let end = start.saturating_add(limit).min(range.next);
+
+    (start..end).filter_map(|position| self.get_indexed_version(key, position)).collect()
+  }
+
+  /// Get the `n` most recent history entries recorded for `key`, from the newest to the oldest.
+  /// Tombstones of removed entries and pruned versions are counted in `n` but not returned
+  pub fn get_latest_n_history(&self, key: K, n: u64) -> Result<Vec<(u64, C)>, ConfigError<K>> {
+    let range = self.get_version_range(key);
+
+    let start = range.next.saturating_sub(n).max(range.first);
+
+    let mut history = Vec::new();
+
+    for version in (start..range.next).rev().filter_map(|position| self.get_indexed_version(key, position)) {
+      let entry = self.get_history_record(version)?;
+
+      if !entry.removed {
+        history.push((version, entry.entry.clone()));
+      }
+    }
+
+    Ok(history)
+  }
+
+  /// Remove the expired history versions of `key` that fall outside the retention policy, from the
+  /// oldest to the newest. Versions which are not expired yet, or never expire, are still in use
+  /// and skipped over. At most `max_items` versions are visited, starting after the last one
+  /// visited by the previous call and wrapping around to the oldest one. Returns the number of
+  /// pruned versions
+  pub fn prune_history(&mut self, key: K, max_items: u64) -> Result<u64, ConfigError<K>> {
+    let Some(history) = self.history.as_ref() else {
+      return Ok(0);
+    };
+
+    // Nothing to prune, the version index is not even read
+    if self.history_retention == HistoryRetentionPolicy::KeepAll {
+      return Ok(0);
+    }
+
+    let mut range = self.get_version_range(key);
+    let initial_range = range;
+
+    // The current version is not the latest one once a scheduled entry is applied after an update
+    let current_version = self.get_current_version(key).ok();
+
+    // Versions from `end` are kept, the latest one is never pruned
+    let end = match self.history_retention {
+      HistoryRetentionPolicy::KeepAll => range.first,
+      HistoryRetentionPolicy::KeepLast(count) => range.next.saturating_sub(count.max(1)),
+      HistoryRetentionPolicy::MaxAge(_) => range.next.saturating_sub(1),
+    };
+
+    // Resume after the versions visited by the previous call, then wrap around to the oldest ones
+    let start = if range.cursor > range.first && range.cursor < end {
+      range.cursor
+    } else {
+      range.first
+    };
+    let mut upper = end;
+    let mut position = start;
+    let mut is_wrapped = false;
+
+    let now = Instant::now();
+    let mut visited_count = 0;
+    let mut pruned_count = 0;
+
+    loop {
+      if position >= upper {
+        if is_wrapped || start == range.first {
+          break;
+        }
+
+        is_wrapped = true;
+        upper = start;
+        position = range.first;
+        continue;
+      }
+
+      if visited_count >= max_items {
+        break;
+      }
+
+      visited_count += 1;
+
+      let is_indexed = match self.get_indexed_version(key, position) {
+        Some(version) => {
+          let record = self.get_history_record(version)?;
+
+          if let HistoryRetentionPolicy::MaxAge(max_age) = self.history_retention {
+            // Versions are ordered by time, the following ones are recent enough
+            let is_outdated = now.checked_sub(record.timestamp).is_some_and(|age| age > max_age);
+            if !is_outdated {
+              position = upper;
+              continue;
+            }
+          }
+
+          // Versions in their grace period or without expiration are still in use
+          let is_expired = Some(version) != current_version && record.expiration_time.is_some_and(|time| time <= now);
+
+          drop(record);
+
+          if is_expired {
+            history.entries.remove(&version);
+            history.versions.remove(&(key, position));
+            pruned_count += 1;
+          }
+
+          !is_expired
+        }
+        None => false,
+      };
+
+      // Pruned versions at the start of the range are not visited again
+      if !is_indexed && position == range.first {
+        range.first += 1;
+      }
+
+      position += 1;
+    }
+
+    range.cursor = position;
+
+    if range != initial_range {
+      history.version_ranges.insert(key, range);
+    }
+
+    Ok(pruned_count)
+  }
+
+  fn get_version_range(&self, key: K) -> VersionRange {
     self
+      .history
+      .as_ref()
+      .and_then(|history| history.version_ranges.get(&key).map(|range| *range))
+      .unwrap_or_default()
+  }
+
+  fn get_indexed_version(&self, key: K, position: u64) -> Option<u64> {
+    self
+      .history
+      .as_ref()
+      .and_then(|history| history.versions.get(&(key, position)).map(|version| *version))
+  }
+
+  /// Remove the scheduled entry of `key` with the version recorded for it
+  fn drop_scheduled_entry(&mut self, key: K) -> Option<ScheduledEntry<C>> {
+    let scheduled_entry = self.scheduled_entries.remove(&key)?;
+
+    if let Some(history) = self.history.as_ref() {
+      history.entries.remove(&scheduled_entry.version);
+      history.versions.remove(&(key, scheduled_entry.position));
+    }
+
+    self.set_scheduled_at(key, None);
+
+    Some(scheduled_entry)
+  }
+
+  fn set_scheduled_at(&mut self, key: K, scheduled_at: Option<Instant>) {
+    if let Some(mut current_entry) = self.current_entries.get_mut(&key) {
+      current_entry.scheduled_at = scheduled_at;
+    }
+  }
+
+  fn get_history_record(&self, version: u64) -> Result<KeyValueEntryRef<'_, ConfigurationEntry<K, C>>, ConfigError<K>> {
+    self
+      .history
+      .as_ref()
+      .ok_or(ConfigError::HistoryDisabled)?
       .entries
-      .get(&ConfigurationKey::Current(key))
-      .map(|e| e.version)
-      .ok_or_else(|| anyhow!(self.get_config_error_message.clone()))
+      .get(&version)
+      .ok_or_else(|| self.history_entry_error(version))
   }
 
-  fn insert_entry(&mut self, config_key: ConfigurationKey<K>, key: K, entry: C, expiration_time: Option<Instant>) -> Result<()> {
-    self.entries.insert(
-      config_key,
-      ConfigurationEntry {
-        key,
+  fn get_history_record_mut(&mut self, version: u64) -> Result<KeyValueEntryRefMut<'_, ConfigurationEntry<K, C>>, ConfigError<K>> {
+    let version_error = self.history_entry_error(version);
+
+    self
+      .history
+      .as_mut()
+      .ok_or(ConfigError::HistoryDisabled)?
+      .entries
+      .get_mut(&version)
+      .ok_or(version_error)
+  }
+
+  fn history_entry_error(&self, version: u64) -> ConfigError<K> {
+    if version < self.version_count {
+      ConfigError::VersionPruned(version)
+    } else {
+      ConfigError::VersionNotFound(version)
+    }
+  }
+
+  fn set_entry(&mut self, key: K, entry: C, increment_version: bool, update_inputs: Option<U>, changeset: Option<u64>) -> Result<(), ConfigError<K>> {
+    let old_entry = self.current_entries.get(&key).map(|e| (e.version, e.scheduled_at, e.key_position));
+    let old_version = old_entry.map(|(version, _, _)| version);
+
+    // A schedule still in the future is kept, a due one was applied before this call
+    let (scheduled_at, key_position) = match old_entry {
+      Some((_, scheduled_at, key_position)) => (scheduled_at.filter(|scheduled_at| !is_due(Some(*scheduled_at))), key_position),
+      None => (None, self.entry_count as u64),
+    };
+
+    let new_version = self.record_history(key, entry.clone(), false, changeset, Instant::now())?;
+
+    self.current_entries.insert(
+      key,
+      CurrentEntry {
         entry,
-        version: self.version_count,
-        expiration_time,
+        version: new_version,
+        scheduled_at,
+        key_position,
       },
     );
+
+    self.prune_history(key, MAX_AUTO_PRUNED_VERSIONS)?;
+
+    if increment_version {
+      self.entry_count += 1;
+
+      if let Some(keys) = self.keys.as_ref() {
+        keys.insert(key_position, key);
+      }
+    }
+
+    match old_version {
+      Some(old_version) => self.emit_event(ConfigEntryUpdated {
+        key,
+        old_version,
+        new_version,
+        update_inputs,
+        changeset,
+      }),
+      None => self.emit_event(ConfigEntryCreated { key, version: new_version }),
+    }
+
     Ok(())
   }
 
-  fn set_entry(&mut self, key: K, entry: C, increment_version: bool) -> Result<()> {
-    self.insert_entry(ConfigurationKey::Current(key), key, entry.clone(), None)?;
+  fn emit_event<E: ScryptoEncode + ScryptoDescribe + ScryptoEvent>(&self, event: E) {
+    if self.emit_events {
+      Runtime::emit_event(event);
+    }
+  }
+
+  /// Allocate the next version to an entry and record it when the history is tracked, the default
+  /// expiration starts at `recorded_at`. Versions are allocated even without history so that current
+  /// entries keep distinct versions
+  fn record_history(&mut self, key: K, entry: C, removed: bool, changeset: Option<u64>, recorded_at: Instant) -> Result<u64, ConfigError<K>> {
+    let version = self.version_count;
+
+    if let Some(history) = self.history.as_ref() {
+      let expiration_time = self.default_expiration_time.map(|exp| add_seconds(recorded_at, exp)).transpose()?;
+
+      history.entries.insert(
+        version,
+        ConfigurationEntry {
+          key,
+          entry,
+          version,
+          expiration_time,
+          timestamp: recorded_at,
+          removed,
+          changeset,
+        },
+      );
 
-    if self.track_history {
-      let expiration_time = self.default_expiration_time.map(|exp| Instant::now().add_seconds(exp as i64).unwrap());
-      self.insert_entry(ConfigurationKey::History(self.version_count), key, entry, expiration_time)?;
-      self.version_count += 1;
+      let mut range = self.get_version_range(key);
+      history.versions.insert((key, range.next), version);
+      range.next += 1;
+      history.version_ranges.insert(key, range);
     }
 
-    if increment_version {
-      self.entry_count += 1;
+    self.version_count += 1;
+
+    Ok(version)
+  }
+}
+
+impl<K: ScryptoSbor + Hash + Eq + Copy + Debug, C: ScryptoSbor + Clone + Updatable<U> + Diffable<U>, U: ScryptoSbor + Clone>
+  ConfigurationManager<K, C, U>
+{
+  /// Get the minimal update inputs turning the entry recorded under `from_version` into the one
+  /// recorded under `to_version`. The changes are returned as update inputs `U` rather than as a list
+  /// of field changes, e.g. an `IndexSet<UpdateMyConfigInput>` for a `GenerateConfig` struct, so
+  /// that they can be passed to `update_entry`. Both versions must belong to the same key
+  pub fn diff_versions(&self, from_version: u64, to_version: u64) -> Result<U, ConfigError<K>> {
+    let (from_key, from_entry) = self.get_history_config(from_version)?;
+    let (to_key, to_entry) = self.get_history_config(to_version)?;
+
+    if from_key != to_key {
+      return Err(ConfigError::VersionKeyMismatch(to_version));
     }
 
-    Ok(())
+    Ok(from_entry.diff(&to_entry))
+  }
+
+  fn get_history_config(&self, version: u64) -> Result<(K, C), ConfigError<K>> {
+    let entry = self.get_history_record(version)?;
+
+    if entry.removed {
+      return Err(ConfigError::Removed(version));
+    }
+
+    Ok((entry.key, entry.entry.clone()))
   }
 }
 
+/// Apply the update inputs to an entry and validate the result
+fn apply_update<K, C: Updatable<U>, U>(entry: &mut C, update_inputs: U) -> Result<(), ConfigError<K>> {
+  entry
+    .update(update_inputs)
+    .and_then(|_| entry.check())
+    .map_err(|err| ConfigError::ValidationFailed(err.to_string()))
+}
+
+/// Check that the retention policy can prune versions recorded with the default expiration
+fn check_retention<K>(default_expiration_time: Option<u64>, history_retention: HistoryRetentionPolicy) -> Result<(), ConfigError<K>> {
+  if default_expiration_time.is_none() && history_retention != HistoryRetentionPolicy::KeepAll {
+    return Err(ConfigError::RetentionWithoutExpiration);
+  }
+
+  Ok(())
+}
+
+/// Whether a scheduled entry with this effective time should replace the current entry
+fn is_due(scheduled_at: Option<Instant>) -> bool {
+  scheduled_at.is_some_and(|time| time <= Instant::now())
+}
+
+/// Add a duration in seconds to an instant. Durations which do not fit in an `i64` are rejected
+/// instead of wrapping around to a negative offset
+pub(crate) fn add_seconds<K>(instant: Instant, seconds: u64) -> Result<Instant, ConfigError<K>> {
+  let offset = i64::try_from(seconds).map_err(|_| ConfigError::InvalidDuration(seconds))?;
+
+  instant.add_seconds(offset).ok_or(ConfigError::InvalidDuration(seconds))
+}
+
 /// Trait for updatable items
 pub trait Updatable<U> {
   fn update(&mut self, inputs: U) -> Result<()>;
   fn check(&self) -> Result<()>;
 }
+
+/// Trait for items which can compute the update inputs turning them into another item
+pub trait Diffable<U> {
+  fn diff(&self, other: &Self) -> U;
+}
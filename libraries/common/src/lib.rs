@@ -1,6 +1,10 @@
+pub mod approval_manager;
 pub mod common_types;
 pub mod config_manager;
 pub mod metadata_setter;
 pub mod prelude;
 pub mod service_manager;
 pub mod service_status;
+
+// Used by the `Updatable` implementations generated by `GenerateConfig`
+pub use anyhow;
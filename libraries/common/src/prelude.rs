@@ -1,3 +1,4 @@
+pub use crate::approval_manager::*;
 pub use crate::common_types::*;
 pub use crate::config_manager::*;
 pub use crate::generate_service_variants;
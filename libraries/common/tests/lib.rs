@@ -86,3 +86,23 @@ fn test_set_roles() {
     assert!(status.set_status(service, false, StatusChangeType::ModeratorSet).is_err());
   }
 }
+
+#[test]
+fn test_config_error_messages() {
+  let key = XRD;
+
+  assert_eq!(
+    ConfigError::<ResourceAddress>::VersionPruned(3).to_string(),
+    "Configuration version 3 has been pruned"
+  );
+
+  assert_eq!(
+    ConfigError::KeyNotFound(key).to_string(),
+    format!("Configuration not found for key {:?}", key)
+  );
+
+  // Config errors can be propagated as `anyhow` errors by the blueprints
+  let error = anyhow::Error::from(ConfigError::<ResourceAddress>::ValidationFailed("Invalid rate".to_string()));
+
+  assert_eq!(error.to_string(), "Configuration validation failed: Invalid rate");
+}
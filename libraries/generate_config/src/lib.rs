@@ -1,20 +1,98 @@
 extern crate proc_macro;
 use inflector::Inflector;
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
 use scrypto::prelude::*;
+use syn::spanned::Spanned;
 use syn::*;
 
-/// Generates code for the `update` and `check` methods of a struct
-/// that implements the `GenerateConfig` trait.
+/// Generates code for the `update`, `check` and `diff` methods of a struct
+/// that implements the `GenerateConfig` trait. The generics of the struct are
+/// propagated to the generated enums, builder and impls.
+///
+/// Each type parameter must be used by a field which is not `#[config(immutable)]`,
+/// otherwise it would be unused in the generated enums and the derive is rejected:
+///
+/// ```compile_fail
+/// # use common::prelude::*;
+/// # use generate_config::GenerateConfig;
+/// # use scrypto::prelude::*;
+/// #[derive(Debug, Clone, GenerateConfig)]
+/// pub struct FrozenConfig<T: Clone + PartialEq + Eq + std::hash::Hash> {
+///   #[config(immutable)]
+///   pub value: T,
+/// }
+/// ```
+///
+/// `update` returns an `UpdateReport` listing, for each input, what it replaced in a
+/// `{Name}FieldValue` and whether it was a no-op: the previous value of value and
+/// `Option` fields, the value of a set input when it was in the set, the previous
+/// key and value of a map entry, and the element at the index of a vec input, or
+/// the whole vec for `Clear` and `Set`.
 ///
 /// The generated code is based on the fields of the struct and the
-/// `check` attribute.
-#[proc_macro_derive(GenerateConfig, attributes(check))]
+/// `check` attributes. A check is either a raw expression on `val`, the
+/// reference to the field value, or a list of built-in checks:
+///
+/// ```ignore
+/// #[check = "val.is_a_rate()"]
+/// #[check(rate)]
+/// #[check(positive)]
+/// #[check(zero_or_positive)]
+/// #[check(non_empty)]
+/// #[check(min = 0, max = "0.95")]
+/// #[check(range = "0..=1")]
+/// #[check(expr = "*val < dec!(0.95)")]
+/// ```
+///
+/// `rate`, `positive` and `zero_or_positive` call the `utils::CanBeChecked` methods.
+/// A `message = "..."` in a check list replaces the default error of its checks,
+/// a `{val}` placeholder, with or without a format spec, is formatted with the value of the field:
+///
+/// ```ignore
+/// #[check(expr = "*val < dec!(0.95)", message = "LTV must be below 0.95, got {val}")]
+/// ```
+///
+/// Set, `Option` and `Vec` fields are updated with `UpdateSetInput`, `UpdateOptionInput`
+/// and `UpdateVecInput`, map fields with a key and an optional value.
+///
+/// A field holding another `GenerateConfig` struct is updated field by field
+/// with `#[config(nested)]`, its `check` running as part of the parent's.
+///
+/// Fields marked `#[config(immutable)]` have no update variant, and
+/// `#[config(role = "admin")]` is returned by the `required_role` method of the
+/// update enum for the variant of the field.
+///
+/// With the struct-level `#[config(updatable)]` attribute, the `Updatable` and
+/// `Diffable` traits of `common::config_manager` are implemented as well, so that
+/// the struct can be stored in a `ConfigurationManager`. The implementations only
+/// refer to `common`, which re-exports the `anyhow` errors they return.
+///
+/// A `{Name}Builder` is generated as well, `#[config(default = "dec!(0.5)")]`
+/// gives the value of a field not set on the builder, other fields are required.
+///
+/// Fields are handled from the name of their type, `#[config(set)]` and
+/// `#[config(map)]` force collection semantics on other types, such as aliases,
+/// with the optional explicit types `set = "T"` and `map = "K, V"`, while
+/// `#[config(value)]` forces a whole value replacement.
+///
+/// Rules involving several fields are expressed on `self` with struct-level
+/// attributes, checked after the per-field checks:
+///
+/// ```ignore
+/// #[config(check = "self.loan_to_value < self.liquidation_threshold", message = "...")]
+/// ```
+#[proc_macro_derive(GenerateConfig, attributes(check, config))]
 pub fn generate_config(input: TokenStream) -> TokenStream {
   let input = parse_macro_input!(input as DeriveInput);
   let name = &input.ident;
 
+  // `struct_config` holds the cross-field checks and options specified in the struct-level `config` attributes
+  let struct_config = match parse_struct_config(&input.attrs) {
+    Ok(struct_config) => struct_config,
+    Err(err) => return err.to_compile_error().into(),
+  };
+
   // The fields of the struct
   let fields = match input.data {
     Data::Struct(ref data_struct) => match data_struct.fields {
@@ -29,6 +107,9 @@ pub fn generate_config(input: TokenStream) -> TokenStream {
   // for example, if the struct is named `Config`, `update_enum_name` will be `UpdateConfigInput`
   let update_enum_name = format_ident!("Update{}Input", name);
 
+  // `field_names` is a vec of the names of the fields of the struct
+  let field_names: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+
   // `update_enum_variants` is a vec of the variants of the enum
   // for example, if the struct has a field named `foo`, `update_enum_variants` will contain the variant `Foo`
   let mut update_enum_variants = Vec::new();
@@ -41,6 +122,44 @@ pub fn generate_config(input: TokenStream) -> TokenStream {
   // for example, if the struct has a field named `foo`, `check_calls` will contain the check `if !(self.check_foo)(&self.foo) { ... }`
   let mut check_calls = Vec::new();
 
+  // `diff_statements` is a vec of the statements collecting the changes in the `diff` method
+  // for example, if the struct has a field named `foo`, `diff_statements` will contain `if self.foo != other.foo { ... }`
+  let mut diff_statements = Vec::new();
+
+  // `nested_inputs` is a vec of the sets collecting the update inputs of the nested fields during the `update` method
+  // for example, if the struct has a nested field named `foo`, `nested_inputs` will contain `let mut foo_inputs = IndexSet::new();`
+  let mut nested_inputs = Vec::new();
+
+  // `nested_updates` is a vec of the calls applying the collected inputs to the nested fields in the `update` method
+  // for example, if the struct has a nested field named `foo`, `nested_updates` will contain `self.foo.update(foo_inputs)?;`
+  let mut nested_updates = Vec::new();
+
+  // `role_match_arms` is a vec of the match arms for the `required_role` method of the enum
+  // for example, if the struct has a field named `foo` with the `admin` role, `role_match_arms` will contain `Foo(..) => Some("admin")`
+  let mut role_match_arms = Vec::new();
+
+  // `field_value_name` is the name of the enum holding the previous values of the updated fields in the update report
+  // for example, if the struct is named `Config`, `field_value_name` will be `ConfigFieldValue`
+  let field_value_name = format_ident!("{}FieldValue", name);
+
+  // `field_value_names` and `field_value_types` are vecs of the names and types of the variants of the field value enum
+  // for example, if the struct has a `foo: Foo` field and a `bar: BTreeSet<Bar>` field, the variants will be `Foo(Foo)` and `Bar(Option<Bar>)`
+  let mut field_value_names = Vec::new();
+  let mut field_value_types = Vec::new();
+
+  // `mutable_field_types` is a vec of the types of the fields which are not immutable, used to check that every type parameter appears in the enums
+  let mut mutable_field_types = Vec::new();
+
+  // `builder_name` is the name of the builder that will be generated
+  // for example, if the struct is named `Config`, `builder_name` will be `ConfigBuilder`
+  let builder_name = format_ident!("{}Builder", name);
+
+  // `builder_fields`, `builder_setters` and `builder_values` are vecs of the fields, setters and field values of the builder
+  // for example, if the struct has a field named `foo`, `builder_fields` will contain `foo: Option<Foo>`
+  let mut builder_fields = Vec::new();
+  let mut builder_setters = Vec::new();
+  let mut builder_values = Vec::new();
+
   // Iterate over the fields of the struct
   for field in fields {
     let field_name = &field.ident;
@@ -50,135 +169,478 @@ pub fn generate_config(input: TokenStream) -> TokenStream {
     // for example, if the field is named `foo`, `variant_name` will be `Foo`
     let variant_name = format_ident!("{}", field_name.as_ref().unwrap().to_string().to_pascal_case());
 
-    // If the field has a `check` attribute, `check_attr` will be `Some`
-    let check_attr = field.attrs.iter().find(|attr| attr.path.is_ident("check"));
+    // `check_fns` is a vec of the closures that will be called in the `check` method, with their optional custom message
+    // if the field has `check` attributes, `check_fns` will contain one closure for each check specified in the attributes
+    // otherwise, `check_fns` will only contain `|val| true`
+    let mut check_fns = Vec::new();
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("check")) {
+      match parse_check_attribute(attr, field_type) {
+        Ok(fns) => check_fns.extend(fns),
+        Err(err) => return err.to_compile_error().into(),
+      }
+    }
+    if check_fns.is_empty() {
+      check_fns.push((quote! { |val| true}, None));
+    }
+
+    let field_config = match parse_field_config(&field.attrs) {
+      Ok(field_config) => field_config,
+      Err(err) => return err.to_compile_error().into(),
+    };
 
-    // `check_fn` is a closure that will be called in the `check` method
-    // if the field has a `check` attribute, `check_fn` will be the closure specified in the attribute
-    // otherwise, `check_fn` will be `|val| true`
-    let check_fn = if let Some(attr) = check_attr {
-      parse_check_attribute(attr, field_type)
-    } else {
-      quote! { |val| true}
+    // `field_kind` decides which update inputs are generated for the field
+    let field_kind = match get_field_kind(field_type, &field_config) {
+      Ok(field_kind) => field_kind,
+      Err(err) => return err.to_compile_error().into(),
     };
 
-    match field_type {
-      Type::Path(type_path) => {
-        let last_segment = type_path.path.segments.last().unwrap();
-        match last_segment.ident.to_string().as_str() {
-          "BTreeSet" | "HashSet" | "IndexSet" => {
-            // `inner_type` is the type of the elements of the set
-            // for example, if the field is a `BTreeSet<i32>`, `inner_type` will be `i32`
-            let inner_type = if let syn::PathArguments::AngleBracketed(args) = &last_segment.arguments {
-              &args.args[0]
-            } else {
-              panic!("Expected Set to have a generic parameter");
-            };
-
-            // Add the variant to the enum
-            update_enum_variants.push(quote! {
-                #variant_name(UpdateSetInput<#inner_type>)
-            });
-
-            // Add the match arm to the `update` method
-            update_impl_match_arms.push(quote! {
-                #update_enum_name::#variant_name(UpdateSetInput::Add(value)) => {
+    if !matches!(field_kind, FieldKind::Immutable) {
+      mutable_field_types.push(field_type);
+    }
+
+    match field_kind {
+      // Immutable fields have no variant in the enum, their checks still run in the `check` method
+      FieldKind::Immutable => {}
+      FieldKind::Nested(nested_path) => {
+        // `nested_enum_path` and `nested_field_value_path` are the paths of the update and field value enums of the nested struct
+        // for example, if the field is an `InterestStrategy`, they will be `UpdateInterestStrategyInput` and `InterestStrategyFieldValue`
+        let nested_enum_path = format_last_ident(&nested_path, |ident| format_ident!("Update{}Input", ident));
+        let nested_field_value_path = format_last_ident(&nested_path, |ident| format_ident!("{}FieldValue", ident));
+
+        let inputs_name = format_ident!("{}_inputs", field_name.as_ref().unwrap());
+
+        // Add the variant to the enums
+        update_enum_variants.push(quote! {
+            #variant_name(#nested_enum_path)
+        });
+        field_value_names.push(variant_name.clone());
+        field_value_types.push(quote! { #nested_field_value_path });
+
+        // The nested inputs are applied together after the loop, so that the nested checks see all of them
+        nested_inputs.push(quote! {
+            let mut #inputs_name = IndexSet::new();
+        });
+        update_impl_match_arms.push(quote! {
+            #update_enum_name::#variant_name(value) => {
+                #inputs_name.insert(value);
+            }
+        });
+        // The changes of the nested update are added to the report
+        nested_updates.push(quote! {
+            if !#inputs_name.is_empty() {
+                for change in self.#field_name.update(#inputs_name)?.changes {
+                    changes.push(UpdateChange {
+                        input: #update_enum_name::#variant_name(change.input),
+                        previous: #field_value_name::#variant_name(change.previous),
+                        is_no_op: change.is_no_op,
+                    });
+                }
+            }
+        });
+
+        // Add the nested changes to the `diff` method
+        diff_statements.push(quote! {
+            for change in self.#field_name.diff(&other.#field_name) {
+                changes.insert(#update_enum_name::#variant_name(change));
+            }
+        });
+      }
+      FieldKind::Set(inner_type) => {
+        // `inner_type` is the type of the elements of the set
+        // for example, if the field is a `BTreeSet<i32>`, `inner_type` will be `i32`
+
+        // Add the variant to the enum
+        update_enum_variants.push(quote! {
+            #variant_name(UpdateSetInput<#inner_type>)
+        });
+
+        // The update report holds the value of the input when it was in the set
+        field_value_names.push(variant_name.clone());
+        field_value_types.push(quote! { Option<#inner_type> });
+
+        // Add the match arm to the `update` method
+        update_impl_match_arms.push(quote! {
+            #update_enum_name::#variant_name(UpdateSetInput::Add(value)) => {
+                let previous = if self.#field_name.contains(&value) {
+                    Some(value)
+                } else {
                     self.#field_name.insert(value);
-                },
-                #update_enum_name::#variant_name(UpdateSetInput::Remove(value)) => {
-                    self.#field_name.remove(&value);
+                    None
+                };
+                let is_no_op = previous.is_some();
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            },
+            #update_enum_name::#variant_name(UpdateSetInput::Remove(value)) => {
+                let previous = if self.#field_name.remove(&value) { Some(value) } else { None };
+                let is_no_op = previous.is_none();
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            }
+        });
+
+        // Add the removed and added values to the `diff` method
+        diff_statements.push(quote! {
+            for value in self.#field_name.iter().filter(|value| !other.#field_name.contains(*value)) {
+                changes.insert(#update_enum_name::#variant_name(UpdateSetInput::Remove(value.clone())));
+            }
+            for value in other.#field_name.iter().filter(|value| !self.#field_name.contains(*value)) {
+                changes.insert(#update_enum_name::#variant_name(UpdateSetInput::Add(value.clone())));
+            }
+        });
+      }
+      FieldKind::Option(inner_type) => {
+        // `inner_type` is the type of the optional value
+        // for example, if the field is an `Option<Decimal>`, `inner_type` will be `Decimal`
+
+        // Add the variant to the enum
+        update_enum_variants.push(quote! {
+            #variant_name(UpdateOptionInput<#inner_type>)
+        });
+
+        // The update report holds the whole previous value
+        field_value_names.push(variant_name.clone());
+        field_value_types.push(quote! { #field_type });
+
+        // Add the match arm to the `update` method
+        update_impl_match_arms.push(quote! {
+            #update_enum_name::#variant_name(UpdateOptionInput::Set(value)) => {
+                let previous = self.#field_name.replace(value);
+                let is_no_op = previous == self.#field_name;
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            },
+            #update_enum_name::#variant_name(UpdateOptionInput::Clear) => {
+                let previous = self.#field_name.take();
+                let is_no_op = previous.is_none();
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            }
+        });
+
+        // Add the value replacement to the `diff` method
+        diff_statements.push(quote! {
+            if self.#field_name != other.#field_name {
+                match &other.#field_name {
+                    Some(value) => changes.insert(#update_enum_name::#variant_name(UpdateOptionInput::Set(value.clone()))),
+                    None => changes.insert(#update_enum_name::#variant_name(UpdateOptionInput::Clear)),
+                };
+            }
+        });
+      }
+      FieldKind::Vec(inner_type) => {
+        // `inner_type` is the type of the elements of the vec
+        // for example, if the field is a `Vec<String>`, `inner_type` will be `String`
+
+        // Add the variant to the enum
+        update_enum_variants.push(quote! {
+            #variant_name(UpdateVecInput<#inner_type>)
+        });
+
+        // The update report holds the element at the index of the input, or the whole vec when it is replaced
+        field_value_names.push(variant_name.clone());
+        field_value_types.push(quote! { PreviousVecValue<#inner_type> });
+
+        // Add the match arm to the `update` method, indexes out of range are rejected
+        update_impl_match_arms.push(quote! {
+            #update_enum_name::#variant_name(UpdateVecInput::Push(value)) => {
+                self.#field_name.push(value);
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(PreviousVecValue::Nothing), is_no_op: false });
+            },
+            #update_enum_name::#variant_name(UpdateVecInput::RemoveAt(index)) => {
+                if index as usize >= self.#field_name.len() {
+                    return Err(format!("Invalid {}::{} index {}", std::stringify!(#name), std::stringify!(#field_name), index));
                 }
-            });
-          }
-          "BTreeMap" | "HashMap" | "IndexMap" => {
-            // `inner_types` is a tuple of the types of the elements of the map
-            // for example, if the field is a `BTreeMap<String,i32>`, `inner_types` will be `(String, i32)`
-            let inner_types = if let syn::PathArguments::AngleBracketed(args) = &last_segment.arguments {
-              let mut types = args.args.iter();
-              (types.next().unwrap(), types.next().unwrap())
-            } else {
-              panic!("Expected IndexMap to have two generic parameters");
-            };
-
-            let (key_type, value_type) = inner_types;
-
-            // Add the variant to the enum
-            update_enum_variants.push(quote! {
-                #variant_name(#key_type, Option<#value_type>)
-            });
-
-            // Add the match arm to the `update` method
-            update_impl_match_arms.push(quote! {
-                #update_enum_name::#variant_name(key, Some(value)) => {
-                    self.#field_name.insert(key, value);
-                },
-                #update_enum_name::#variant_name(key, None) => {
-                    self.#field_name.remove(&key);
+                let previous = self.#field_name.remove(index as usize);
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(PreviousVecValue::Element(previous)), is_no_op: false });
+            },
+            #update_enum_name::#variant_name(UpdateVecInput::Replace(index, value)) => {
+                match self.#field_name.get_mut(index as usize) {
+                    Some(element) => {
+                        let previous = std::mem::replace(element, value);
+                        let is_no_op = previous == *element;
+                        changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(PreviousVecValue::Element(previous)), is_no_op });
+                    }
+                    None => return Err(format!("Invalid {}::{} index {}", std::stringify!(#name), std::stringify!(#field_name), index)),
                 }
-            });
-          }
-          _ => {
-            // Add the variant to the enum
-            update_enum_variants.push(quote! {
-                #variant_name(#field_type)
-            });
-
-            // Add the match arm to the `update` method
-            update_impl_match_arms.push(quote! {
-                #update_enum_name::#variant_name(value) => {
-                    self.#field_name = value;
+            },
+            #update_enum_name::#variant_name(UpdateVecInput::Clear) => {
+                let previous = std::mem::take(&mut self.#field_name);
+                let is_no_op = previous.is_empty();
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(PreviousVecValue::All(previous)), is_no_op });
+            },
+            #update_enum_name::#variant_name(UpdateVecInput::Set(value)) => {
+                let previous = std::mem::replace(&mut self.#field_name, value);
+                let is_no_op = previous == self.#field_name;
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(PreviousVecValue::All(previous)), is_no_op });
+            }
+        });
+
+        // Add the replaced, removed and pushed elements to the `diff` method
+        // removals start from the end so that the indexes stay valid, and identical pushes,
+        // which would be merged in the set of changes, fall back to replacing the whole vec
+        diff_statements.push(quote! {
+            if self.#field_name != other.#field_name {
+                let common_len = self.#field_name.len().min(other.#field_name.len());
+                let pushed = &other.#field_name[common_len..];
+
+                if (1..pushed.len()).any(|index| pushed[..index].contains(&pushed[index])) {
+                    changes.insert(#update_enum_name::#variant_name(UpdateVecInput::Set(other.#field_name.clone())));
+                } else {
+                    for index in (0..common_len).filter(|index| self.#field_name[*index] != other.#field_name[*index]) {
+                        changes.insert(#update_enum_name::#variant_name(UpdateVecInput::Replace(index as u32, other.#field_name[index].clone())));
+                    }
+                    for index in (common_len..self.#field_name.len()).rev() {
+                        changes.insert(#update_enum_name::#variant_name(UpdateVecInput::RemoveAt(index as u32)));
+                    }
+                    for value in pushed {
+                        changes.insert(#update_enum_name::#variant_name(UpdateVecInput::Push(value.clone())));
+                    }
                 }
-            });
-          }
-        }
+            }
+        });
       }
-      _ => {
+      FieldKind::Map(key_type, value_type) => {
+        // `key_type` and `value_type` are the types of the keys and values of the map
+        // for example, if the field is a `BTreeMap<String, i32>`, they will be `String` and `i32`
+
+        // Add the variant to the enum
+        update_enum_variants.push(quote! {
+            #variant_name(#key_type, Option<#value_type>)
+        });
+
+        // The update report holds the previous key and value of the entry
+        field_value_names.push(variant_name.clone());
+        field_value_types.push(quote! { Option<(#key_type, #value_type)> });
+
+        // Add the match arm to the `update` method
+        update_impl_match_arms.push(quote! {
+            #update_enum_name::#variant_name(key, Some(value)) => {
+                let previous = self.#field_name.insert(key.clone(), value);
+                let is_no_op = previous.as_ref() == self.#field_name.get(&key);
+                let previous = previous.map(|previous| (key, previous));
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            },
+            #update_enum_name::#variant_name(key, None) => {
+                let previous = self.#field_name.remove(&key).map(|previous| (key, previous));
+                let is_no_op = previous.is_none();
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            }
+        });
+
+        // Add the removed and inserted entries to the `diff` method
+        diff_statements.push(quote! {
+            for key in self.#field_name.keys().filter(|key| !other.#field_name.contains_key(*key)) {
+                changes.insert(#update_enum_name::#variant_name(key.clone(), None));
+            }
+            for (key, value) in other.#field_name.iter().filter(|(key, value)| self.#field_name.get(*key) != Some(*value)) {
+                changes.insert(#update_enum_name::#variant_name(key.clone(), Some(value.clone())));
+            }
+        });
+      }
+      FieldKind::Value => {
         // Add the variant to the enum
         update_enum_variants.push(quote! {
             #variant_name(#field_type)
         });
 
+        // The update report holds the whole previous value
+        field_value_names.push(variant_name.clone());
+        field_value_types.push(quote! { #field_type });
+
         // Add the match arm to the `update` method
         update_impl_match_arms.push(quote! {
             #update_enum_name::#variant_name(value) => {
-                self.#field_name = value;
+                let previous = std::mem::replace(&mut self.#field_name, value);
+                let is_no_op = previous == self.#field_name;
+                changes.push(UpdateChange { input, previous: #field_value_name::#variant_name(previous), is_no_op });
+            }
+        });
+
+        // Add the value replacement to the `diff` method
+        diff_statements.push(quote! {
+            if self.#field_name != other.#field_name {
+                changes.insert(#update_enum_name::#variant_name(other.#field_name.clone()));
             }
         });
       }
     }
 
-    // Add the check call to the `check` method
+    // Add the nested check to the `check` method
+    if field_config.nested {
+      check_calls.push(quote! {
+          self.#field_name.check()?;
+      });
+    }
+
+    // Add the match arm to the `required_role` method, nested variants defer to the role of the nested input
+    if !field_config.immutable {
+      role_match_arms.push(match (&field_config.role, field_config.nested) {
+        (Some(role), _) => quote! { #update_enum_name::#variant_name(..) => Some(#role) },
+        (None, true) => quote! { #update_enum_name::#variant_name(ref input) => input.required_role() },
+        (None, false) => quote! { #update_enum_name::#variant_name(..) => None },
+      });
+    }
+
+    // Add the field to the builder, fields without a default value are required
+    builder_fields.push(quote! { #field_name: Option<#field_type> });
+    builder_setters.push(quote! {
+        pub fn #field_name(mut self, value: #field_type) -> Self {
+            self.#field_name = Some(value);
+            self
+        }
+    });
+    builder_values.push(match &field_config.default {
+      Some(default) => quote! { #field_name: self.#field_name.unwrap_or_else(|| #default) },
+      None => quote! {
+          #field_name: self.#field_name.ok_or_else(|| {
+              format!("Missing {}::{}", std::stringify!(#name), std::stringify!(#field_name))
+          })?
+      },
+    });
+
+    // Add the check calls to the `check` method
+    for (check_fn, message) in check_fns {
+      let message = match message {
+        // The field value is only passed when the message references it, unused named arguments do not compile
+        Some(message) if formats_val(&message.value()) => quote! { format!(#message, val = self.#field_name) },
+        Some(message) => quote! { format!(#message) },
+        None => quote! {
+            format!(
+                "Invalid {}::{}",
+                std::stringify!(#name),
+                std::stringify!(#field_name)
+            )
+        },
+      };
+      check_calls.push(quote! {
+          if !(#check_fn)(&self.#field_name) {
+              return Err(#message);
+          }
+      });
+    }
+  }
+
+  // Add the cross-field checks to the `check` method, after the per-field checks
+  for (check_expr, check_source, message) in struct_config.checks {
+    let message = match message {
+      Some(message) => quote! { #message.to_string() },
+      None => quote! { format!("Invalid {}: {}", std::stringify!(#name), #check_source) },
+    };
     check_calls.push(quote! {
-        if !(#check_fn)(&self.#field_name) {
-            return Err(
-                format!(
-                    "Invalid {}::{}",
-                    std::stringify!(#name),
-                    std::stringify!(#field_name)
-                )
-            );
+        if !(#check_expr) {
+            return Err(#message);
         }
     });
   }
 
+  // The generics and where clause of the struct are propagated to the enum, the builder and the impls
+  // for example, if the struct is `Config<T>`, the enum will be `UpdateConfigInput<T>`
+  let generics = &input.generics;
+
+  // A type parameter only used by immutable fields would be unused in the update and field value enums
+  let unused_param = generics
+    .type_params()
+    .find(|param| !mutable_field_types.iter().any(|ty| type_mentions(ty, &param.ident)));
+  if let Some(param) = unused_param {
+    return syn::Error::new_spanned(
+      param,
+      "Type parameters must be used by at least one field which is not `#[config(immutable)]`",
+    )
+    .to_compile_error()
+    .into();
+  }
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+  // The type parameters of the enum can be wrapped in collections, which require them to be categorized
+  let categorize_types = generics.type_params().map(|param| param.ident.to_string()).collect::<Vec<_>>().join(", ");
+  let sbor_attribute = if categorize_types.is_empty() {
+    quote! {}
+  } else {
+    quote! { #[sbor(categorize_types = #categorize_types)] }
+  };
+
+  // Without variants, no change is ever added to the report of the `update` method
+  let changes_declaration = if update_enum_variants.is_empty() {
+    quote! { let changes = Vec::new(); }
+  } else {
+    quote! { let mut changes = Vec::new(); }
+  };
+
   // Generate the expanded code
   let expanded = quote! {
       #[derive(ScryptoSbor, ManifestSbor, Debug, Clone, PartialEq, Eq, Hash)]
-      pub enum #update_enum_name {
+      #sbor_attribute
+      pub enum #update_enum_name #generics #where_clause {
           #(#update_enum_variants),*
       }
 
-      impl #name {
-          pub fn update(&mut self, config_inputs: IndexSet<#update_enum_name>) -> Result<(), String> {
-              for config_input in config_inputs {
-                  match config_input {
+      impl #impl_generics #update_enum_name #ty_generics #where_clause {
+          /// Returns the role required to apply the update input, if any
+          pub fn required_role(&self) -> Option<&'static str> {
+              match *self {
+                  #(#role_match_arms),*
+              }
+          }
+      }
+
+      /// Builds a checked configuration from default values and overrides
+      pub struct #builder_name #generics #where_clause {
+          #(#builder_fields),*
+      }
+
+      impl #impl_generics Default for #builder_name #ty_generics #where_clause {
+          fn default() -> Self {
+              Self {
+                  #(#field_names: None),*
+              }
+          }
+      }
+
+      /// Previous value of a field updated by an input of `#update_enum_name`
+      #[derive(ScryptoSbor, Debug, Clone, PartialEq)]
+      #sbor_attribute
+      pub enum #field_value_name #generics #where_clause {
+          #(#field_value_names(#field_value_types),)*
+      }
+
+      impl #impl_generics #builder_name #ty_generics #where_clause {
+          #(#builder_setters)*
+
+          /// Returns the configuration, failing if a required field is missing or if the checks fail
+          pub fn build(self) -> Result<#name #ty_generics, String> {
+              let config = #name {
+                  #(#builder_values),*
+              };
+
+              config.check()?;
+
+              Ok(config)
+          }
+      }
+
+      impl #impl_generics #name #ty_generics #where_clause {
+          pub fn builder() -> #builder_name #ty_generics {
+              #builder_name::default()
+          }
+
+          /// Applies the update inputs and checks the result, returning the change made by each input
+          pub fn update(
+              &mut self,
+              config_inputs: IndexSet<#update_enum_name #ty_generics>,
+          ) -> Result<UpdateReport<#update_enum_name #ty_generics, #field_value_name #ty_generics>, String> {
+              #changes_declaration
+
+              #(#nested_inputs)*
+
+              // Each input reports the change it made, nested inputs are reported by the nested update
+              for input in config_inputs {
+                  match input.clone() {
                       #(#update_impl_match_arms),*
-                  };
+                  }
               }
 
+              #(#nested_updates)*
+
               self.check()?;
 
-              Ok(())
+              Ok(UpdateReport { changes })
           }
 
           pub fn check(&self) -> Result<(),String> {
@@ -186,19 +648,526 @@ pub fn generate_config(input: TokenStream) -> TokenStream {
 
               Ok(())
           }
+
+          /// Returns the minimal set of update inputs turning `self` into `other`
+          pub fn diff(&self, other: &Self) -> IndexSet<#update_enum_name #ty_generics> {
+              let mut changes = IndexSet::new();
+
+              #(#diff_statements)*
+
+              changes
+          }
       }
   };
 
-  TokenStream::from(expanded)
+  // Implement the `Updatable` and `Diffable` traits of the configuration manager, delegating to the inherent methods
+  let config_manager_impls = if struct_config.updatable {
+    quote! {
+        impl #impl_generics ::common::config_manager::Updatable<IndexSet<#update_enum_name #ty_generics>> for #name #ty_generics #where_clause {
+            fn update(&mut self, inputs: IndexSet<#update_enum_name #ty_generics>) -> ::common::anyhow::Result<()> {
+                #name::update(self, inputs).map(|_| ()).map_err(|err| ::common::anyhow::anyhow!(err))
+            }
+
+            fn check(&self) -> ::common::anyhow::Result<()> {
+                #name::check(self).map_err(|err| ::common::anyhow::anyhow!(err))
+            }
+        }
+
+        impl #impl_generics ::common::config_manager::Diffable<IndexSet<#update_enum_name #ty_generics>> for #name #ty_generics #where_clause {
+            fn diff(&self, other: &Self) -> IndexSet<#update_enum_name #ty_generics> {
+                #name::diff(self, other)
+            }
+        }
+    }
+  } else {
+    quote! {}
+  };
+
+  TokenStream::from(quote! {
+      #expanded
+      #config_manager_impls
+  })
+}
+
+/// Options given with the field-level `config` attributes
+#[derive(Default)]
+struct FieldConfig {
+  nested: bool,
+  immutable: bool,
+  role: Option<LitStr>,
+  default: Option<Expr>,
+  collection: Option<CollectionConfig>,
+}
+
+/// Collection semantics forced with the `set`, `map` and `value` field attributes, with the optional explicit types
+#[allow(clippy::large_enum_variant)]
+enum CollectionConfig {
+  Set(Option<Type>),
+  Map(Option<(Type, Type)>),
+  Value,
+}
+
+fn parse_field_config(attrs: &[Attribute]) -> syn::Result<FieldConfig> {
+  let mut field_config = FieldConfig::default();
+
+  for attr in attrs.iter().filter(|attr| attr.path.is_ident("config")) {
+    let list = match attr.parse_meta()? {
+      Meta::List(list) => list,
+      meta => return Err(syn::Error::new_spanned(meta, "Expected `#[config(...)]`")),
+    };
+
+    for nested in list.nested.iter() {
+      match nested {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("nested") => field_config.nested = true,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("immutable") => field_config.immutable = true,
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+          path,
+          lit: Lit::Str(lit_str),
+          ..
+        }))
+          if path.is_ident("role") =>
+        {
+          field_config.role = Some(lit_str.clone())
+        }
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+          path,
+          lit: Lit::Str(lit_str),
+          ..
+        }))
+          if path.is_ident("default") =>
+        {
+          field_config.default = Some(lit_str.parse()?)
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("set") => set_collection(&mut field_config, CollectionConfig::Set(None), nested)?,
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+          path,
+          lit: Lit::Str(lit_str),
+          ..
+        }))
+          if path.is_ident("set") =>
+        {
+          set_collection(&mut field_config, CollectionConfig::Set(Some(lit_str.parse()?)), nested)?
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("map") => set_collection(&mut field_config, CollectionConfig::Map(None), nested)?,
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+          path,
+          lit: Lit::Str(lit_str),
+          ..
+        }))
+          if path.is_ident("map") =>
+        {
+          let types = lit_str.parse_with(punctuated::Punctuated::<Type, Token![,]>::parse_terminated)?;
+          let (key_type, value_type) = match types.iter().collect::<Vec<_>>().as_slice() {
+            [key_type, value_type] => ((*key_type).clone(), (*value_type).clone()),
+            _ => {
+              return Err(syn::Error::new_spanned(
+                lit_str,
+                "Expected the key and value types of the map, such as `map = \"K, V\"`",
+              ))
+            }
+          };
+          set_collection(&mut field_config, CollectionConfig::Map(Some((key_type, value_type))), nested)?
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("value") => set_collection(&mut field_config, CollectionConfig::Value, nested)?,
+        nested => {
+          return Err(syn::Error::new_spanned(
+            nested,
+            "Unsupported config attribute, expected `nested`, `immutable`, `role = \"..\"`, `default = \"..\"`, `set`, `map` or `value`",
+          ))
+        }
+      }
+    }
+
+    if field_config.nested && field_config.collection.is_some() {
+      return Err(syn::Error::new_spanned(list, "A nested field cannot have collection semantics"));
+    }
+
+    if field_config.immutable && field_config.role.is_some() {
+      return Err(syn::Error::new_spanned(list, "An immutable field cannot have an update role"));
+    }
+  }
+
+  Ok(field_config)
+}
+
+fn set_collection(field_config: &mut FieldConfig, collection: CollectionConfig, nested: &NestedMeta) -> syn::Result<()> {
+  if field_config.collection.is_some() {
+    return Err(syn::Error::new_spanned(nested, "Only one of `set`, `map` and `value` can be given"));
+  }
+  field_config.collection = Some(collection);
+  Ok(())
+}
+
+/// How the update inputs of a field are generated
+#[allow(clippy::large_enum_variant)]
+enum FieldKind {
+  Immutable,
+  Nested(Path),
+  Set(Type),
+  Map(Type, Type),
+  Option(Type),
+  Vec(Type),
+  Value,
+}
+
+const SET_TYPES: [&str; 3] = ["BTreeSet", "HashSet", "IndexSet"];
+const MAP_TYPES: [&str; 3] = ["BTreeMap", "HashMap", "IndexMap"];
+
+/// Returns the kind of a field, from its config attributes or from the name of its type
+fn get_field_kind(ty: &Type, field_config: &FieldConfig) -> syn::Result<FieldKind> {
+  if field_config.immutable {
+    return Ok(FieldKind::Immutable);
+  }
+
+  if field_config.nested {
+    return match ty {
+      Type::Path(type_path) => Ok(FieldKind::Nested(type_path.path.clone())),
+      _ => Err(syn::Error::new_spanned(ty, "`nested` config is only supported on struct fields")),
+    };
+  }
+
+  // Elements are inserted and removed in place, which shared pointers do not allow
+  if matches!(field_config.collection, Some(CollectionConfig::Set(_) | CollectionConfig::Map(_))) && is_shared_pointer(ty) {
+    return Err(syn::Error::new_spanned(
+      ty,
+      "`set` and `map` cannot update a collection behind an `Rc` or an `Arc`, use `#[config(value)]`",
+    ));
+  }
+
+  match &field_config.collection {
+    Some(CollectionConfig::Value) => Ok(FieldKind::Value),
+    Some(CollectionConfig::Set(Some(inner_type))) => Ok(FieldKind::Set(inner_type.clone())),
+    Some(CollectionConfig::Map(Some((key_type, value_type)))) => Ok(FieldKind::Map(key_type.clone(), value_type.clone())),
+    // Without explicit types, they are the generic arguments of the field type, looking through a `Box`
+    Some(CollectionConfig::Set(None)) => match generic_type_args(unwrap_box(ty)).as_slice() {
+      [inner_type, ..] => Ok(FieldKind::Set((*inner_type).clone())),
+      _ => Err(syn::Error::new_spanned(
+        ty,
+        "Cannot infer the element type of the set, use `set = \"..\"`",
+      )),
+    },
+    Some(CollectionConfig::Map(None)) => match generic_type_args(unwrap_box(ty)).as_slice() {
+      [key_type, value_type, ..] => Ok(FieldKind::Map((*key_type).clone(), (*value_type).clone())),
+      _ => Err(syn::Error::new_spanned(
+        ty,
+        "Cannot infer the key and value types of the map, use `map = \"K, V\"`",
+      )),
+    },
+    None => match (type_name(ty).as_deref(), generic_type_args(ty).as_slice()) {
+      (Some(name), [inner_type, ..]) if SET_TYPES.contains(&name) => Ok(FieldKind::Set((*inner_type).clone())),
+      (Some(name), [key_type, value_type, ..]) if MAP_TYPES.contains(&name) => Ok(FieldKind::Map((*key_type).clone(), (*value_type).clone())),
+      (Some("Option"), [inner_type]) => Ok(FieldKind::Option((*inner_type).clone())),
+      (Some("Vec"), [inner_type]) => Ok(FieldKind::Vec((*inner_type).clone())),
+      (Some("KeyValueStore"), _) => Err(syn::Error::new_spanned(
+        ty,
+        "KeyValueStore fields can neither be replaced nor diffed, use `#[config(immutable)]`",
+      )),
+      (Some("Box"), [inner_type]) if is_known_collection(inner_type) => Err(syn::Error::new_spanned(
+        ty,
+        "Ambiguous wrapped collection, use `#[config(set)]`, `#[config(map)]` or `#[config(value)]`",
+      )),
+      (Some("Rc" | "Arc"), [inner_type]) if is_known_collection(inner_type) => Err(syn::Error::new_spanned(
+        ty,
+        "Ambiguous shared collection, use `#[config(value)]` to replace it as a whole",
+      )),
+      _ => Ok(FieldKind::Value),
+    },
+  }
+}
+
+fn is_shared_pointer(ty: &Type) -> bool {
+  matches!(type_name(ty).as_deref(), Some("Rc" | "Arc"))
+}
+
+fn is_known_collection(ty: &Type) -> bool {
+  type_name(ty).is_some_and(|name| SET_TYPES.contains(&name.as_str()) || MAP_TYPES.contains(&name.as_str()) || name == "Option" || name == "Vec")
+}
+
+/// Returns the generic type arguments of the last segment of a path type
+fn generic_type_args(ty: &Type) -> Vec<&Type> {
+  match ty {
+    Type::Path(type_path) => match &type_path.path.segments.last().unwrap().arguments {
+      PathArguments::AngleBracketed(args) => args
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+          GenericArgument::Type(ty) => Some(ty),
+          _ => None,
+        })
+        .collect(),
+      _ => vec![],
+    },
+    _ => vec![],
+  }
+}
+
+/// Returns the path with the identifier of its last segment formatted, keeping its generic arguments
+fn format_last_ident(path: &Path, format: impl Fn(&Ident) -> Ident) -> Path {
+  let mut path = path.clone();
+  let last_segment = path.segments.last_mut().unwrap();
+  last_segment.ident = format(&last_segment.ident);
+  path
+}
+
+/// Returns the type wrapped in a `Box`, or the type itself
+fn unwrap_box(ty: &Type) -> &Type {
+  match (type_name(ty).as_deref(), generic_type_args(ty).as_slice()) {
+    (Some("Box"), [inner_type]) => unwrap_box(inner_type),
+    _ => ty,
+  }
+}
+
+/// Options given with the struct-level `config` attributes
+#[derive(Default)]
+struct StructConfig {
+  /// Cross-field check expressions, with their source and optional message
+  checks: Vec<(Expr, LitStr, Option<LitStr>)>,
+  updatable: bool,
+}
+
+fn parse_struct_config(attrs: &[Attribute]) -> syn::Result<StructConfig> {
+  let mut struct_config = StructConfig::default();
+
+  for attr in attrs.iter().filter(|attr| attr.path.is_ident("config")) {
+    let list = match attr.parse_meta()? {
+      Meta::List(list) => list,
+      meta => return Err(syn::Error::new_spanned(meta, "Expected `#[config(...)]`")),
+    };
+
+    let mut check = None;
+    let mut message = None;
+    for nested in list.nested.iter() {
+      match nested {
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+          path,
+          lit: Lit::Str(lit_str),
+          ..
+        }))
+          if path.is_ident("check") =>
+        {
+          check = Some((lit_str.parse::<Expr>()?, lit_str.clone()));
+        }
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+          path,
+          lit: Lit::Str(lit_str),
+          ..
+        }))
+          if path.is_ident("message") =>
+        {
+          message = Some(lit_str.clone());
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("updatable") => struct_config.updatable = true,
+        nested => {
+          return Err(syn::Error::new_spanned(
+            nested,
+            "Unsupported config attribute, expected `check = \"..\"`, `message = \"..\"` or `updatable`",
+          ))
+        }
+      }
+    }
+
+    match (check, message) {
+      (Some((check, check_source)), message) => struct_config.checks.push((check, check_source, message)),
+      (None, Some(_)) => return Err(syn::Error::new_spanned(list, "`message` requires a `check` in the same config attribute")),
+      (None, None) => {}
+    }
+  }
+
+  Ok(struct_config)
 }
 
-fn parse_check_attribute(attr: &Attribute, ty: &Type) -> proc_macro2::TokenStream {
-  let meta = attr.parse_meta().expect("Failed to parse attribute");
-  if let Meta::NameValue(name_value) = meta {
-    if let Lit::Str(lit_str) = name_value.lit {
-      let check_fn: proc_macro2::TokenStream = lit_str.value().parse().expect("Failed to parse check function");
-      return quote! { |val:&#ty| #check_fn };
+fn parse_check_attribute(attr: &Attribute, ty: &Type) -> syn::Result<Vec<(proc_macro2::TokenStream, Option<LitStr>)>> {
+  match attr.parse_meta()? {
+    // `#[check = "..."]` is a raw expression on `val`
+    Meta::NameValue(MetaNameValue { lit: Lit::Str(lit_str), .. }) => {
+      let check_fn: proc_macro2::TokenStream = lit_str.parse()?;
+      Ok(vec![(quote! { |val:&#ty| #check_fn }, None)])
     }
+    // `#[check(...)]` is a list of built-in checks, sharing an optional `message`
+    Meta::List(list) => {
+      let mut message = None;
+      let mut check_fns = Vec::new();
+      for nested in list.nested.iter() {
+        match nested {
+          NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Str(lit_str),
+            ..
+          }))
+            if path.is_ident("message") =>
+          {
+            message = Some(lit_str.clone());
+          }
+          NestedMeta::Meta(meta) => check_fns.push(parse_builtin_check(meta, ty)?),
+          NestedMeta::Lit(lit) => return Err(syn::Error::new_spanned(lit, "Expected a built-in check such as `rate` or `min = 0`")),
+        }
+      }
+      if check_fns.is_empty() {
+        return Err(syn::Error::new_spanned(list, "`message` requires a check in the same check attribute"));
+      }
+      Ok(check_fns.into_iter().map(|check_fn| (check_fn, message.clone())).collect())
+    }
+    meta => Err(syn::Error::new_spanned(meta, "Invalid check attribute syntax")),
+  }
+}
+
+fn parse_builtin_check(meta: &Meta, ty: &Type) -> syn::Result<proc_macro2::TokenStream> {
+  // Type errors, for example a `rate` check on a field not implementing `CanBeChecked`, are reported on the field type
+  let span = ty.span();
+  let check_name = meta.path().get_ident().map(|ident| ident.to_string()).unwrap_or_default();
+
+  match (check_name.as_str(), meta) {
+    ("rate", Meta::Path(_)) => Ok(quote_spanned! {span=> |val:&#ty| ::utils::CanBeChecked::is_a_rate(val) }),
+    ("positive", Meta::Path(_)) => Ok(quote_spanned! {span=> |val:&#ty| ::utils::CanBeChecked::is_positive(val) }),
+    ("zero_or_positive", Meta::Path(_)) => Ok(quote_spanned! {span=> |val:&#ty| ::utils::CanBeChecked::is_zero_or_positive(val) }),
+    ("non_empty", Meta::Path(_)) => {
+      if numeric_type_name(ty).is_some() || type_name(ty).as_deref() == Some("bool") {
+        return Err(syn::Error::new_spanned(
+          ty,
+          "`non_empty` check is only supported on strings and collections",
+        ));
+      }
+      Ok(quote_spanned! {span=> |val:&#ty| !val.is_empty() })
+    }
+    ("min", Meta::NameValue(name_value)) => {
+      let bound = parse_numeric_bound(&parse_bound_lit(&name_value.lit)?, ty)?;
+      Ok(quote! { |val:&#ty| *val >= #bound })
+    }
+    ("max", Meta::NameValue(name_value)) => {
+      let bound = parse_numeric_bound(&parse_bound_lit(&name_value.lit)?, ty)?;
+      Ok(quote! { |val:&#ty| *val <= #bound })
+    }
+    ("expr", Meta::NameValue(MetaNameValue { lit: Lit::Str(lit_str), .. })) => {
+      let check_fn: Expr = lit_str.parse()?;
+      Ok(quote! { |val:&#ty| #check_fn })
+    }
+    ("range", Meta::NameValue(MetaNameValue { lit: Lit::Str(lit_str), .. })) => {
+      let range: ExprRange = lit_str.parse()?;
+      if range.from.is_none() && range.to.is_none() {
+        return Err(syn::Error::new_spanned(lit_str, "`range` check must have at least one bound"));
+      }
+      let from = range.from.as_deref().map(|from| parse_numeric_bound(from, ty)).transpose()?;
+      let to = range.to.as_deref().map(|to| parse_numeric_bound(to, ty)).transpose()?;
+      let limits = range.limits;
+      Ok(quote! { |val:&#ty| (#from #limits #to).contains(val) })
+    }
+    _ => Err(syn::Error::new_spanned(
+      meta,
+      "Unsupported check, expected `rate`, `positive`, `zero_or_positive`, `non_empty`, `min = ..`, `max = ..`, `range = \"..\"` or `expr = \"..\"`",
+    )),
+  }
+}
+
+/// Bounds are given as numeric literals, or as strings for negative values
+fn parse_bound_lit(lit: &Lit) -> syn::Result<Expr> {
+  match lit {
+    Lit::Str(lit_str) => lit_str.parse(),
+    Lit::Int(_) | Lit::Float(_) => Ok(Expr::Lit(ExprLit {
+      attrs: vec![],
+      lit: lit.clone(),
+    })),
+    _ => Err(syn::Error::new_spanned(lit, "Expected a numeric bound")),
+  }
+}
+
+/// Converts a bound to the type of the field, decimal bounds are built with the `dec!` and `pdec!` macros
+fn parse_numeric_bound(bound: &Expr, ty: &Type) -> syn::Result<proc_macro2::TokenStream> {
+  match numeric_type_name(ty).as_deref() {
+    Some("Decimal") => Ok(quote! { dec!(#bound) }),
+    Some("PreciseDecimal") => Ok(quote! { pdec!(#bound) }),
+    Some("f32" | "f64") => {
+      let bound = float_bound(bound);
+      Ok(quote! { #bound })
+    }
+    Some(_) => Ok(quote! { #bound }),
+    None => Err(syn::Error::new_spanned(
+      ty,
+      "Bound checks are only supported on integer, float and decimal fields",
+    )),
+  }
+}
+
+/// Integer literals do not coerce to floats, `min = 0` on a float field is turned into `0.0`
+fn float_bound(bound: &Expr) -> Expr {
+  match bound {
+    Expr::Lit(ExprLit {
+      attrs,
+      lit: Lit::Int(lit_int),
+    }) => Expr::Lit(ExprLit {
+      attrs: attrs.clone(),
+      lit: Lit::Float(LitFloat::new(&format!("{}.0", lit_int.base10_digits()), lit_int.span())),
+    }),
+    Expr::Unary(unary) => Expr::Unary(ExprUnary {
+      expr: Box::new(float_bound(&unary.expr)),
+      ..unary.clone()
+    }),
+    _ => bound.clone(),
+  }
+}
+
+fn numeric_type_name(ty: &Type) -> Option<String> {
+  type_name(ty).filter(|name| {
+    matches!(
+      name.as_str(),
+      "u8"
+        | "u16"
+        | "u32"
+        | "u64"
+        | "u128"
+        | "usize"
+        | "i8"
+        | "i16"
+        | "i32"
+        | "i64"
+        | "i128"
+        | "isize"
+        | "f32"
+        | "f64"
+        | "Decimal"
+        | "PreciseDecimal"
+    )
+  })
+}
+
+/// Whether the identifier appears in the type, for example the type parameter `T` in `Vec<T>`
+fn type_mentions(ty: &Type, ident: &Ident) -> bool {
+  tokens_mention(ty.to_token_stream(), ident)
+}
+
+fn tokens_mention(tokens: proc_macro2::TokenStream, ident: &Ident) -> bool {
+  tokens.into_iter().any(|token| match token {
+    proc_macro2::TokenTree::Ident(token_ident) => token_ident == *ident,
+    proc_macro2::TokenTree::Group(group) => tokens_mention(group.stream(), ident),
+    _ => false,
+  })
+}
+
+/// Whether the format string has a `{val}` placeholder, with or without a format spec, escaped braces are skipped
+fn formats_val(format: &str) -> bool {
+  let mut rest = format;
+  while let Some(start) = rest.find(['{', '}']) {
+    let after = &rest[start + 1..];
+    if after.starts_with(&rest[start..start + 1]) {
+      rest = &after[1..];
+      continue;
+    }
+    if rest[start..].starts_with('}') {
+      rest = after;
+      continue;
+    }
+    let end = after.find(['}', ':']).unwrap_or(after.len());
+    if after[..end].trim() == "val" {
+      return true;
+    }
+    rest = &after[end..];
+  }
+  false
+}
+
+/// Returns the identifier of the last segment of a path type
+fn type_name(ty: &Type) -> Option<String> {
+  match ty {
+    Type::Path(type_path) => type_path.path.segments.last().map(|segment| segment.ident.to_string()),
+    _ => None,
   }
-  panic!("Invalid check attribute syntax");
 }
@@ -1,6 +1,7 @@
 use common::prelude::*;
 use generate_config::GenerateConfig;
 use scrypto::prelude::*;
+use utils::check_field_invalidity;
 
 #[derive(Debug, GenerateConfig)]
 pub struct TestConfig {
@@ -53,6 +54,661 @@ fn main2() {
   // assert!(config.check().is_ok());
 }
 
+#[test]
+fn diff() {
+  let mut config = TestConfig {
+    valuator_component: GENESIS_HELPER,
+    valuator_method: "method".to_string(),
+    is_enabled: true,
+    rate: dec!(0.5),
+    underlying_resources: btreeset!("resource1".to_string(), "resource2".to_string()),
+    resource_map: btreemap!("resource_key1".to_string() => dec!(1.2), "resource_key2".to_string() => dec!(1.3)),
+  };
+
+  let other_config = TestConfig {
+    valuator_component: GENESIS_HELPER,
+    valuator_method: "other_method".to_string(),
+    is_enabled: true,
+    rate: dec!(0.5),
+    underlying_resources: btreeset!("resource2".to_string(), "resource3".to_string()),
+    resource_map: btreemap!("resource_key1".to_string() => dec!(1.2), "resource_key2".to_string() => dec!(0.4), "resource_key3".to_string() => dec!(1.0)),
+  };
+
+  assert!(config.diff(&config).is_empty());
+
+  let changes = config.diff(&other_config);
+
+  assert_eq!(
+    changes,
+    indexset!(
+      UpdateTestConfigInput::ValuatorMethod("other_method".to_string()),
+      UpdateTestConfigInput::UnderlyingResources(UpdateSetInput::Remove("resource1".to_string())),
+      UpdateTestConfigInput::UnderlyingResources(UpdateSetInput::Add("resource3".to_string())),
+      UpdateTestConfigInput::ResourceMap("resource_key2".to_string(), Some(dec!(0.4))),
+      UpdateTestConfigInput::ResourceMap("resource_key3".to_string(), Some(dec!(1.0)))
+    )
+  );
+
+  config.update(changes).unwrap();
+
+  assert!(config.diff(&other_config).is_empty());
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct CheckedConfig {
+  #[check(rate)]
+  pub rate: Decimal,
+  #[check(positive, max = "0.95")]
+  pub loan_to_value: Decimal,
+  #[check(range = "-1..=1")]
+  pub adjustment: Decimal,
+  #[check(min = 1)]
+  pub max_items: u64,
+  #[check(non_empty)]
+  pub name: String,
+  // Float fields cannot be hashed in the update enum
+  #[config(immutable)]
+  #[check(min = 0, max = "0.5")]
+  pub fee: f64,
+  #[config(immutable)]
+  #[check(range = "-1..1")]
+  pub skew: f32,
+}
+
+fn get_default_checked_config() -> CheckedConfig {
+  CheckedConfig {
+    rate: dec!(0.5),
+    loan_to_value: dec!(0.8),
+    adjustment: dec!(-0.5),
+    max_items: 10,
+    name: "market".to_string(),
+    fee: 0.1,
+    skew: 0.0,
+  }
+}
+
+check_field_invalidity!(
+  CheckedConfig,
+  get_default_checked_config,
+  rate,
+  [dec!(0), dec!(0.5), dec!(1)],
+  [dec!(-0.1), dec!(1.1)]
+);
+
+check_field_invalidity!(
+  CheckedConfig,
+  get_default_checked_config,
+  loan_to_value,
+  [dec!(0.1), dec!(0.95)],
+  [dec!(0), dec!(-0.1), dec!(0.96)]
+);
+
+check_field_invalidity!(
+  CheckedConfig,
+  get_default_checked_config,
+  adjustment,
+  [dec!(-1), dec!(0), dec!(1)],
+  [dec!(-1.1), dec!(1.1)]
+);
+
+check_field_invalidity!(CheckedConfig, get_default_checked_config, max_items, [1, 100], [0]);
+
+check_field_invalidity!(CheckedConfig, get_default_checked_config, name, ["market".to_string()], ["".to_string()]);
+
+check_field_invalidity!(CheckedConfig, get_default_checked_config, fee, [0.0, 0.5], [-0.1, 0.6]);
+
+check_field_invalidity!(CheckedConfig, get_default_checked_config, skew, [-1.0, 0.0, 0.9], [-1.1, 1.0]);
+
+#[derive(Debug, Clone, GenerateConfig)]
+#[config(check = "self.loan_to_value < self.liquidation_threshold")]
+#[config(check = "self.optimal_usage < Decimal::ONE", message = "Optimal usage must be below 1")]
+pub struct CrossCheckedConfig {
+  #[check(rate)]
+  pub loan_to_value: Decimal,
+  #[check(rate)]
+  pub liquidation_threshold: Decimal,
+  #[check(rate)]
+  pub optimal_usage: Decimal,
+}
+
+#[test]
+fn cross_field_checks() {
+  let mut config = CrossCheckedConfig {
+    loan_to_value: dec!(0.7),
+    liquidation_threshold: dec!(0.8),
+    optimal_usage: dec!(0.9),
+  };
+
+  assert!(config.check().is_ok());
+
+  assert_eq!(
+    config.clone().update(indexset!(UpdateCrossCheckedConfigInput::LoanToValue(dec!(0.8)))),
+    Err("Invalid CrossCheckedConfig: self.loan_to_value < self.liquidation_threshold".to_string())
+  );
+
+  assert_eq!(
+    config.clone().update(indexset!(UpdateCrossCheckedConfigInput::OptimalUsage(dec!(1)))),
+    Err("Optimal usage must be below 1".to_string())
+  );
+
+  // Per-field checks run before the cross-field checks
+  assert_eq!(
+    config
+      .clone()
+      .update(indexset!(UpdateCrossCheckedConfigInput::LiquidationThreshold(dec!(1.1)))),
+    Err("Invalid CrossCheckedConfig::liquidation_threshold".to_string())
+  );
+
+  config
+    .update(indexset!(
+      UpdateCrossCheckedConfigInput::LoanToValue(dec!(0.85)),
+      UpdateCrossCheckedConfigInput::LiquidationThreshold(dec!(0.9))
+    ))
+    .unwrap();
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct MessageConfig {
+  #[check(expr = "*val < dec!(0.95)", message = "LTV must be below 0.95, got {val}")]
+  pub loan_to_value: Decimal,
+  #[check(min = 1, max = 100, message = "Max items must be between 1 and 100, got {val:?}")]
+  pub max_items: u64,
+  #[check(rate, message = "Fee must be a {{valid}} rate")]
+  pub fee: Decimal,
+}
+
+#[test]
+fn check_messages() {
+  let config = MessageConfig {
+    loan_to_value: dec!(0.8),
+    max_items: 10,
+    fee: dec!(0.01),
+  };
+
+  assert!(config.check().is_ok());
+
+  assert_eq!(
+    config.clone().update(indexset!(UpdateMessageConfigInput::LoanToValue(dec!(0.97)))),
+    Err("LTV must be below 0.95, got 0.97".to_string())
+  );
+
+  assert_eq!(
+    config.clone().update(indexset!(UpdateMessageConfigInput::MaxItems(0))),
+    Err("Max items must be between 1 and 100, got 0".to_string())
+  );
+
+  assert_eq!(
+    config.clone().update(indexset!(UpdateMessageConfigInput::Fee(dec!(2)))),
+    Err("Fee must be a {valid} rate".to_string())
+  );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, ScryptoSbor, GenerateConfig)]
+#[config(check = "self.optimal_usage < Decimal::ONE")]
+pub struct InterestStrategy {
+  #[check(zero_or_positive)]
+  pub base_rate: Decimal,
+  #[check(rate)]
+  pub optimal_usage: Decimal,
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct MarketConfig {
+  #[check(rate)]
+  pub loan_to_value: Decimal,
+  #[config(nested)]
+  pub interest_strategy: InterestStrategy,
+}
+
+#[test]
+fn nested_config() {
+  let mut config = MarketConfig {
+    loan_to_value: dec!(0.7),
+    interest_strategy: InterestStrategy {
+      base_rate: dec!(0.01),
+      optimal_usage: dec!(0.8),
+    },
+  };
+
+  config
+    .update(indexset!(UpdateMarketConfigInput::InterestStrategy(
+      UpdateInterestStrategyInput::BaseRate(dec!(0.02))
+    )))
+    .unwrap();
+
+  assert_eq!(config.interest_strategy.base_rate, dec!(0.02));
+  assert_eq!(config.interest_strategy.optimal_usage, dec!(0.8));
+
+  // The nested checks are part of the parent's
+  assert_eq!(
+    config.clone().update(indexset!(UpdateMarketConfigInput::InterestStrategy(
+      UpdateInterestStrategyInput::OptimalUsage(dec!(1))
+    ))),
+    Err("Invalid InterestStrategy: self.optimal_usage < Decimal::ONE".to_string())
+  );
+
+  config.interest_strategy.base_rate = dec!(-1);
+  assert_eq!(config.check(), Err("Invalid InterestStrategy::base_rate".to_string()));
+  config.interest_strategy.base_rate = dec!(0.02);
+
+  let mut other_config = config.clone();
+  other_config.interest_strategy.optimal_usage = dec!(0.9);
+
+  assert_eq!(
+    config.diff(&other_config),
+    indexset!(UpdateMarketConfigInput::InterestStrategy(UpdateInterestStrategyInput::OptimalUsage(
+      dec!(0.9)
+    )))
+  );
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct SourcesConfig {
+  pub fallback_rate: Option<Decimal>,
+  pub fee_tiers: Vec<Decimal>,
+  pub price_sources: Vec<String>,
+}
+
+#[test]
+fn option_and_vec_fields() {
+  let mut config = get_default_sources_config();
+
+  config
+    .update(indexset!(
+      UpdateSourcesConfigInput::FallbackRate(UpdateOptionInput::Set(dec!(0.05))),
+      UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::Push(dec!(0.03))),
+      UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::RemoveAt(0)),
+      UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::Replace(0, dec!(0.015))),
+      UpdateSourcesConfigInput::PriceSources(UpdateVecInput::Clear)
+    ))
+    .unwrap();
+
+  assert_eq!(config.fallback_rate, Some(dec!(0.05)));
+  assert_eq!(config.fee_tiers, vec![dec!(0.015), dec!(0.03)]);
+  assert!(config.price_sources.is_empty());
+
+  assert_eq!(
+    config
+      .clone()
+      .update(indexset!(UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::RemoveAt(2)))),
+    Err("Invalid SourcesConfig::fee_tiers index 2".to_string())
+  );
+  assert_eq!(
+    config
+      .clone()
+      .update(indexset!(UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::Replace(2, dec!(0.1))))),
+    Err("Invalid SourcesConfig::fee_tiers index 2".to_string())
+  );
+
+  let other_config = SourcesConfig {
+    fallback_rate: None,
+    fee_tiers: vec![dec!(0.015)],
+    price_sources: vec!["source2".to_string(), "source3".to_string()],
+  };
+
+  let changes = config.diff(&other_config);
+
+  assert_eq!(
+    changes,
+    indexset!(
+      UpdateSourcesConfigInput::FallbackRate(UpdateOptionInput::Clear),
+      UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::RemoveAt(1)),
+      UpdateSourcesConfigInput::PriceSources(UpdateVecInput::Push("source2".to_string())),
+      UpdateSourcesConfigInput::PriceSources(UpdateVecInput::Push("source3".to_string()))
+    )
+  );
+
+  config.update(changes).unwrap();
+  assert!(config.diff(&other_config).is_empty());
+
+  // Identical pushes would be merged in the set of changes
+  let other_config = SourcesConfig {
+    price_sources: vec!["source2".to_string(), "source2".to_string()],
+    ..other_config
+  };
+
+  let changes = config.diff(&other_config);
+  config.update(changes).unwrap();
+  assert!(config.diff(&other_config).is_empty());
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct PoolConfig {
+  #[config(immutable)]
+  #[check(non_empty)]
+  pub underlying_resource: String,
+  #[config(role = "admin")]
+  #[check(rate)]
+  pub max_utilization: Decimal,
+  pub deposit_limit: Decimal,
+  #[config(nested, role = "admin")]
+  pub interest_strategy: InterestStrategy,
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct FrozenConfig {
+  #[config(immutable)]
+  pub pool_unit: String,
+}
+
+#[test]
+fn immutable_and_role_fields() {
+  let mut config = PoolConfig {
+    underlying_resource: "resource".to_string(),
+    max_utilization: dec!(0.9),
+    deposit_limit: dec!(1000),
+    interest_strategy: InterestStrategy {
+      base_rate: dec!(0.01),
+      optimal_usage: dec!(0.8),
+    },
+  };
+
+  assert_eq!(UpdatePoolConfigInput::MaxUtilization(dec!(0.8)).required_role(), Some("admin"));
+  assert_eq!(UpdatePoolConfigInput::DepositLimit(dec!(500)).required_role(), None);
+  assert_eq!(
+    UpdatePoolConfigInput::InterestStrategy(UpdateInterestStrategyInput::BaseRate(dec!(0.02))).required_role(),
+    Some("admin")
+  );
+
+  // Changes to immutable fields cannot be expressed
+  let mut other_config = config.clone();
+  other_config.underlying_resource = "other_resource".to_string();
+  assert!(config.diff(&other_config).is_empty());
+
+  // Checks of immutable fields still run
+  config.underlying_resource = "".to_string();
+  assert_eq!(config.check(), Err("Invalid PoolConfig::underlying_resource".to_string()));
+
+  let mut frozen_config = FrozenConfig {
+    pool_unit: "pool_unit".to_string(),
+  };
+  assert!(frozen_config.update(IndexSet::new()).is_ok());
+}
+
+#[derive(Debug, Clone, ScryptoSbor, GenerateConfig)]
+#[config(updatable)]
+pub struct ManagedConfig {
+  #[check(rate)]
+  pub rate: Decimal,
+  pub price_sources: BTreeSet<String>,
+}
+
+/// Compiles only if the config can be stored in a `ConfigurationManager`
+fn assert_manageable<C: ScryptoSbor + Clone + Updatable<U> + Diffable<U>, U: ScryptoSbor + Clone>(_config: &C) {}
+
+#[test]
+fn updatable_config() {
+  let mut config = ManagedConfig {
+    rate: dec!(0.5),
+    price_sources: BTreeSet::new(),
+  };
+
+  assert_manageable(&config);
+
+  Updatable::update(&mut config, indexset!(UpdateManagedConfigInput::Rate(dec!(0.6)))).unwrap();
+  assert_eq!(config.rate, dec!(0.6));
+
+  let err = Updatable::update(&mut config, indexset!(UpdateManagedConfigInput::Rate(dec!(1.1)))).unwrap_err();
+  assert_eq!(err.to_string(), "Invalid ManagedConfig::rate");
+  assert!(Updatable::check(&config).is_err());
+
+  let mut other_config = config.clone();
+  other_config.rate = dec!(0.6);
+  other_config.price_sources.insert("source".to_string());
+  assert_eq!(
+    Diffable::diff(&config, &other_config),
+    indexset!(
+      UpdateManagedConfigInput::Rate(dec!(0.6)),
+      UpdateManagedConfigInput::PriceSources(UpdateSetInput::Add("source".to_string()))
+    )
+  );
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct DefaultedConfig {
+  #[check(non_empty)]
+  pub name: String,
+  #[config(default = "dec!(0.5)")]
+  #[check(rate)]
+  pub loan_to_value: Decimal,
+  #[config(default = "BTreeSet::new()")]
+  pub price_sources: BTreeSet<String>,
+}
+
+#[test]
+fn builder() {
+  let config = DefaultedConfig::builder().name("market".to_string()).build().unwrap();
+
+  assert_eq!(config.name, "market");
+  assert_eq!(config.loan_to_value, dec!(0.5));
+  assert!(config.price_sources.is_empty());
+
+  let config = DefaultedConfig::builder()
+    .name("market".to_string())
+    .loan_to_value(dec!(0.7))
+    .build()
+    .unwrap();
+
+  assert_eq!(config.loan_to_value, dec!(0.7));
+
+  assert_eq!(
+    DefaultedConfig::builder().build().unwrap_err(),
+    "Missing DefaultedConfig::name".to_string()
+  );
+
+  assert_eq!(
+    DefaultedConfig::builder()
+      .name("market".to_string())
+      .loan_to_value(dec!(1.1))
+      .build()
+      .unwrap_err(),
+    "Invalid DefaultedConfig::loan_to_value".to_string()
+  );
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+#[config(updatable)]
+pub struct AdapterConfig<T>
+where
+  T: Clone + PartialEq + Eq + std::hash::Hash,
+{
+  pub adapter: T,
+  pub fallback_adapters: Vec<T>,
+  #[check(rate)]
+  pub rate: Decimal,
+}
+
+#[test]
+fn generic_config() {
+  let mut config = AdapterConfig::builder()
+    .adapter(GENESIS_HELPER)
+    .fallback_adapters(vec![])
+    .rate(dec!(0.5))
+    .build()
+    .unwrap();
+
+  config
+    .update(indexset!(
+      UpdateAdapterConfigInput::Adapter(CONSENSUS_MANAGER),
+      UpdateAdapterConfigInput::FallbackAdapters(UpdateVecInput::Push(GENESIS_HELPER))
+    ))
+    .unwrap();
+
+  assert_eq!(config.adapter, CONSENSUS_MANAGER);
+  assert_eq!(config.fallback_adapters, vec![GENESIS_HELPER]);
+
+  let mut other_config = config.clone();
+  other_config.fallback_adapters.clear();
+  assert_eq!(
+    Diffable::diff(&config, &other_config),
+    indexset!(UpdateAdapterConfigInput::FallbackAdapters(UpdateVecInput::RemoveAt(0)))
+  );
+
+  let encoded = scrypto_encode(&UpdateAdapterConfigInput::Adapter(CONSENSUS_MANAGER)).unwrap();
+  assert_eq!(
+    scrypto_decode::<UpdateAdapterConfigInput<ComponentAddress>>(&encoded).unwrap(),
+    UpdateAdapterConfigInput::Adapter(CONSENSUS_MANAGER)
+  );
+}
+
+pub type PriceSources = BTreeSet<String>;
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct ForcedKindConfig {
+  #[config(set = "String")]
+  pub price_sources: PriceSources,
+  #[config(map)]
+  pub fee_tiers: Box<IndexMap<String, Decimal>>,
+  #[config(value)]
+  pub allowed_resources: BTreeSet<String>,
+}
+
+#[test]
+fn forced_field_kinds() {
+  let mut config = ForcedKindConfig {
+    price_sources: PriceSources::new(),
+    fee_tiers: Box::default(),
+    allowed_resources: BTreeSet::new(),
+  };
+
+  config
+    .update(indexset!(
+      UpdateForcedKindConfigInput::PriceSources(UpdateSetInput::Add("source".to_string())),
+      UpdateForcedKindConfigInput::FeeTiers("tier".to_string(), Some(dec!(0.01))),
+      UpdateForcedKindConfigInput::AllowedResources(btreeset!("resource".to_string()))
+    ))
+    .unwrap();
+
+  assert!(config.price_sources.contains("source"));
+  assert_eq!(config.fee_tiers.get("tier"), Some(&dec!(0.01)));
+  assert!(config.allowed_resources.contains("resource"));
+
+  let mut other_config = config.clone();
+  other_config.fee_tiers.swap_remove("tier");
+  assert_eq!(
+    config.diff(&other_config),
+    indexset!(UpdateForcedKindConfigInput::FeeTiers("tier".to_string(), None))
+  );
+}
+
+#[test]
+fn update_report() {
+  let mut config = MarketConfig {
+    loan_to_value: dec!(0.7),
+    interest_strategy: InterestStrategy {
+      base_rate: dec!(0.01),
+      optimal_usage: dec!(0.8),
+    },
+  };
+
+  let report = config
+    .update(indexset!(
+      UpdateMarketConfigInput::LoanToValue(dec!(0.6)),
+      UpdateMarketConfigInput::InterestStrategy(UpdateInterestStrategyInput::BaseRate(dec!(0.01)))
+    ))
+    .unwrap();
+
+  assert!(!report.is_no_op());
+  assert_eq!(
+    report.changes,
+    vec![
+      UpdateChange {
+        input: UpdateMarketConfigInput::LoanToValue(dec!(0.6)),
+        previous: MarketConfigFieldValue::LoanToValue(dec!(0.7)),
+        is_no_op: false,
+      },
+      UpdateChange {
+        input: UpdateMarketConfigInput::InterestStrategy(UpdateInterestStrategyInput::BaseRate(dec!(0.01))),
+        previous: MarketConfigFieldValue::InterestStrategy(InterestStrategyFieldValue::BaseRate(dec!(0.01))),
+        is_no_op: true,
+      },
+    ]
+  );
+
+  let mut config = get_default_sources_config();
+
+  // Removing a missing element or setting the current value changes nothing
+  let report = config
+    .update(indexset!(
+      UpdateSourcesConfigInput::FallbackRate(UpdateOptionInput::Clear),
+      UpdateSourcesConfigInput::PriceSources(UpdateVecInput::Set(vec!["source1".to_string()]))
+    ))
+    .unwrap();
+
+  assert!(report.is_no_op());
+  assert_eq!(
+    report.changes[1].previous,
+    SourcesConfigFieldValue::PriceSources(PreviousVecValue::All(vec!["source1".to_string()]))
+  );
+
+  let report = config
+    .update(indexset!(UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::Push(dec!(0.03)))))
+    .unwrap();
+
+  assert!(!report.is_no_op());
+  assert_eq!(report.changes[0].previous, SourcesConfigFieldValue::FeeTiers(PreviousVecValue::Nothing));
+
+  // Indexed inputs only report the element at their index
+  let report = config
+    .update(indexset!(UpdateSourcesConfigInput::FeeTiers(UpdateVecInput::Replace(1, dec!(0.05)))))
+    .unwrap();
+
+  assert_eq!(
+    report.changes[0].previous,
+    SourcesConfigFieldValue::FeeTiers(PreviousVecValue::Element(dec!(0.02)))
+  );
+
+  // Set inputs report the membership of their value and map inputs the previous value of their entry
+  let mut config = ForcedKindConfig {
+    price_sources: btreeset!("source".to_string()),
+    fee_tiers: Box::new(indexmap!("tier".to_string() => dec!(0.01))),
+    allowed_resources: BTreeSet::new(),
+  };
+
+  let report = config
+    .update(indexset!(
+      UpdateForcedKindConfigInput::PriceSources(UpdateSetInput::Add("source".to_string())),
+      UpdateForcedKindConfigInput::PriceSources(UpdateSetInput::Remove("other".to_string())),
+      UpdateForcedKindConfigInput::FeeTiers("tier".to_string(), Some(dec!(0.02))),
+      UpdateForcedKindConfigInput::FeeTiers("new_tier".to_string(), None)
+    ))
+    .unwrap();
+
+  let changes: Vec<_> = report.changes.into_iter().map(|change| (change.previous, change.is_no_op)).collect();
+  assert_eq!(
+    changes,
+    vec![
+      (ForcedKindConfigFieldValue::PriceSources(Some("source".to_string())), true),
+      (ForcedKindConfigFieldValue::PriceSources(None), true),
+      (ForcedKindConfigFieldValue::FeeTiers(Some(("tier".to_string(), dec!(0.01)))), false),
+      (ForcedKindConfigFieldValue::FeeTiers(None), true),
+    ]
+  );
+
+  // The removed set element is reported with the type parameter of the set
+  let mut config = TaggedConfig { tags: btreeset!(1u8) };
+
+  let report = config
+    .update(indexset!(UpdateTaggedConfigInput::Tags(UpdateSetInput::Remove(1))))
+    .unwrap();
+
+  assert_eq!(report.changes[0].previous, TaggedConfigFieldValue::Tags(Some(1)));
+  assert!(config.tags.is_empty());
+}
+
+#[derive(Debug, Clone, GenerateConfig)]
+pub struct TaggedConfig<T: Clone + Ord + std::hash::Hash> {
+  pub tags: BTreeSet<T>,
+}
+
+fn get_default_sources_config() -> SourcesConfig {
+  SourcesConfig {
+    fallback_rate: None,
+    fee_tiers: vec![dec!(0.01), dec!(0.02)],
+    price_sources: vec!["source1".to_string()],
+  }
+}
+
 trait CanBeChecked {
   fn is_a_rate(&self) -> bool;
 }
@@ -0,0 +1,226 @@
+use common::prelude::*;
+use generate_config::GenerateConfig;
+use scrypto::prelude::*;
+
+pub type TesterConfigInputs = IndexSet<UpdateTesterConfigInput>;
+
+// Events are registered under the name of their type, which must match their `EVENT_NAME`
+pub type ConfigEntryCreated = common::config_manager::ConfigEntryCreated<u16>;
+pub type ConfigEntryUpdated = common::config_manager::ConfigEntryUpdated<u16, TesterConfigInputs>;
+pub type ConfigEntryRemoved = common::config_manager::ConfigEntryRemoved<u16>;
+pub type ConfigVersionExpired = common::config_manager::ConfigVersionExpired<u16>;
+
+#[derive(ScryptoSbor, ManifestSbor, Debug, Clone, PartialEq, GenerateConfig)]
+#[config(updatable)]
+pub struct TesterConfig {
+  #[check(rate)]
+  pub rate: Decimal,
+  #[check(range = "1..=100")]
+  pub max_items: u64,
+}
+
+// Exposes a `ConfigurationManager` and an `ApprovalManager` so that their behaviour can be tested on ledger.
+// Errors are returned as their message
+#[blueprint]
+#[events(ConfigEntryCreated, ConfigEntryUpdated, ConfigEntryRemoved, ConfigVersionExpired)]
+mod config_manager_tester {
+  struct ConfigManagerTester {
+    config_manager: ConfigurationManager<u16, TesterConfig, TesterConfigInputs>,
+    approval_manager: ApprovalManager<u16, TesterConfigInputs>,
+  }
+
+  impl ConfigManagerTester {
+    pub fn instantiate(
+      default_expiration_time: Option<u64>,
+      history_retention: Option<HistoryRetentionPolicy>,
+      approvers: IndexSet<NonFungibleGlobalId>,
+      quorum: u8,
+      proposal_expiration_time: Option<u64>,
+    ) -> Global<ConfigManagerTester> {
+      // Without a retention policy, neither the history nor the keys are tracked
+      let with_history = history_retention.is_some();
+      let stores = ConfigurationStores {
+        current_entries: KeyValueStore::new(),
+        pending_updates: KeyValueStore::new(),
+        scheduled_entries: KeyValueStore::new(),
+        keys: with_history.then(KeyValueStore::new),
+        history: with_history.then(|| HistoryStores {
+          entries: KeyValueStore::new(),
+          versions: KeyValueStore::new(),
+          version_ranges: KeyValueStore::new(),
+        }),
+      };
+
+      let config_manager = ConfigurationManager::new(
+        default_expiration_time,
+        history_retention.unwrap_or(HistoryRetentionPolicy::KeepAll),
+        true,
+        stores,
+      )
+      .unwrap_or_else(|err| panic!("{}", err));
+
+      let approval_manager =
+        ApprovalManager::new(approvers, quorum, proposal_expiration_time, KeyValueStore::new()).unwrap_or_else(|err| panic!("{}", err));
+
+      Self {
+        config_manager,
+        approval_manager,
+      }
+      .instantiate()
+      .prepare_to_globalize(OwnerRole::None)
+      .globalize()
+    }
+
+    // Configuration manager
+
+    pub fn update_default_expiration(&mut self, new_default_expiration: Option<u64>) -> Result<(), String> {
+      self
+        .config_manager
+        .update_default_expiration(new_default_expiration)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn update_history_retention(&mut self, new_history_retention: HistoryRetentionPolicy) -> Result<(), String> {
+      self
+        .config_manager
+        .update_history_retention(new_history_retention)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn new_entry(&mut self, key: u16, entry: TesterConfig) -> Result<(), String> {
+      self.config_manager.new_entry(key, entry).map_err(|err| err.to_string())
+    }
+
+    pub fn remove_entry(&mut self, key: u16) -> Result<(), String> {
+      self.config_manager.remove_entry(key).map_err(|err| err.to_string())
+    }
+
+    pub fn update_entry(&mut self, key: u16, update_inputs: TesterConfigInputs) -> Result<(), String> {
+      self.config_manager.update_entry(key, update_inputs).map_err(|err| err.to_string())
+    }
+
+    pub fn update_entry_with_expiration(&mut self, key: u16, update_inputs: TesterConfigInputs, expiration_time: Option<u64>) -> Result<(), String> {
+      self
+        .config_manager
+        .update_entry_with_expiration(key, update_inputs, expiration_time)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn update_entries(&mut self, updates: Vec<(u16, TesterConfigInputs)>) -> Result<u64, String> {
+      self.config_manager.update_entries(updates).map_err(|err| err.to_string())
+    }
+
+    pub fn propose_update(&mut self, key: u16, update_inputs: TesterConfigInputs, delay_seconds: u64) -> Result<Instant, String> {
+      self
+        .config_manager
+        .propose_update(key, update_inputs, delay_seconds)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn execute_pending(&mut self, key: u16) -> Result<(), String> {
+      self.config_manager.execute_pending(key).map_err(|err| err.to_string())
+    }
+
+    pub fn rollback_to_version(&mut self, key: u16, version: u64) -> Result<(), String> {
+      self.config_manager.rollback_to_version(key, version).map_err(|err| err.to_string())
+    }
+
+    pub fn schedule_entry(&mut self, key: u16, entry: TesterConfig, effective_at: Instant) -> Result<(), String> {
+      self
+        .config_manager
+        .schedule_entry(key, entry, effective_at)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn cancel_scheduled_entry(&mut self, key: u16) -> Result<(), String> {
+      self.config_manager.cancel_scheduled_entry(key).map_err(|err| err.to_string())
+    }
+
+    pub fn apply_scheduled_entry(&mut self, key: u16) -> Result<bool, String> {
+      self.config_manager.apply_scheduled_entry(key).map_err(|err| err.to_string())
+    }
+
+    pub fn extend_expiration(&mut self, version: u64, seconds: u64) -> Result<(), String> {
+      self.config_manager.extend_expiration(version, seconds).map_err(|err| err.to_string())
+    }
+
+    pub fn set_entry_expired(&mut self, version: u64) -> Result<(), String> {
+      self.config_manager.set_entry_expired(version).map_err(|err| err.to_string())
+    }
+
+    pub fn prune_history(&mut self, key: u16, max_items: u64) -> Result<u64, String> {
+      self.config_manager.prune_history(key, max_items).map_err(|err| err.to_string())
+    }
+
+    pub fn get_entry_count(&self) -> u16 {
+      self.config_manager.get_entry_count()
+    }
+
+    pub fn get_current_entry(&self, key: u16) -> Result<TesterConfig, String> {
+      self.config_manager.get_current_entry(key).map_err(|err| err.to_string())
+    }
+
+    pub fn get_current_version(&self, key: u16) -> Result<u64, String> {
+      self.config_manager.get_current_version(key).map_err(|err| err.to_string())
+    }
+
+    pub fn get_history_entry(&self, version: u64) -> Result<(TesterConfig, bool), String> {
+      self.config_manager.get_history_entry(version).map_err(|err| err.to_string())
+    }
+
+    pub fn get_history_changeset(&self, version: u64) -> Result<Option<u64>, String> {
+      self.config_manager.get_history_changeset(version).map_err(|err| err.to_string())
+    }
+
+    pub fn get_versions_for_key(&self, key: u16, offset: u64, limit: u64) -> Vec<u64> {
+      self.config_manager.get_versions_for_key(key, offset, limit)
+    }
+
+    pub fn get_latest_n_history(&self, key: u16, n: u64) -> Result<Vec<(u64, TesterConfig)>, String> {
+      self.config_manager.get_latest_n_history(key, n).map_err(|err| err.to_string())
+    }
+
+    pub fn get_all_current_entries(&self, offset: u64, limit: u64) -> Result<Vec<(u16, u64, TesterConfig)>, String> {
+      self.config_manager.get_all_current_entries(offset, limit).map_err(|err| err.to_string())
+    }
+
+    pub fn diff_versions(&self, from_version: u64, to_version: u64) -> Result<TesterConfigInputs, String> {
+      self.config_manager.diff_versions(from_version, to_version).map_err(|err| err.to_string())
+    }
+
+    // Approval manager
+
+    pub fn update_approvers(&mut self, approvers: IndexSet<NonFungibleGlobalId>, quorum: u8) -> Result<(), String> {
+      self.approval_manager.update_approvers(approvers, quorum).map_err(|err| err.to_string())
+    }
+
+    pub fn propose(&mut self, proposer: NonFungibleGlobalId, key: u16, update_inputs: TesterConfigInputs) -> Result<u64, String> {
+      self
+        .approval_manager
+        .propose(proposer, key, update_inputs, &mut self.config_manager)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn approve(&mut self, approver: NonFungibleGlobalId, proposal_id: u64) -> Result<bool, String> {
+      self
+        .approval_manager
+        .approve(approver, proposal_id, &mut self.config_manager)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn execute_proposal(&mut self, proposal_id: u64) -> Result<bool, String> {
+      self
+        .approval_manager
+        .execute_proposal(proposal_id, &mut self.config_manager)
+        .map_err(|err| err.to_string())
+    }
+
+    pub fn cancel_proposal(&mut self, caller: NonFungibleGlobalId, proposal_id: u64) -> Result<(), String> {
+      self.approval_manager.cancel_proposal(caller, proposal_id).map_err(|err| err.to_string())
+    }
+
+    pub fn get_proposal(&self, proposal_id: u64) -> Result<Proposal<u16, TesterConfigInputs>, String> {
+      self.approval_manager.get_proposal(proposal_id).map_err(|err| err.to_string())
+    }
+  }
+}
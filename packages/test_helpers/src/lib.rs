@@ -1,4 +1,5 @@
 pub mod blueprints {
+  pub mod config_manager_tester;
   pub mod dummy_client_app;
   pub mod nft_locker_faucet;
   pub mod resource_faucet;
@@ -0,0 +1,155 @@
+mod harness;
+
+use common::prelude::{ApprovalError, ConfigError, HistoryRetentionPolicy};
+use harness::{Tester, HOUR};
+use scrypto_test::prelude::*;
+use test_helpers::blueprints::config_manager_tester::{TesterConfig, TesterConfigInputs, UpdateTesterConfigInput};
+
+/// Instantiates a tester with the signers 0, 1 and 2 as approvers, a quorum of 2 and proposals
+/// expiring after an hour
+fn setup() -> Tester {
+  let mut tester = Tester::new(None, HistoryRetentionPolicy::KeepAll, &[0, 1, 2], 2, Some(HOUR));
+
+  tester
+    .new_entry(
+      1,
+      TesterConfig {
+        rate: dec!(0.5),
+        max_items: 10,
+      },
+    )
+    .unwrap();
+
+  tester
+}
+
+fn rate_update(rate: Decimal) -> TesterConfigInputs {
+  indexset!(UpdateTesterConfigInput::Rate(rate))
+}
+
+fn current_rate(tester: &mut Tester) -> Decimal {
+  tester.get_current_entry(1).unwrap().rate
+}
+
+fn error(err: ApprovalError<u16>) -> String {
+  err.to_string()
+}
+
+#[test]
+fn update_is_applied_once_the_quorum_is_reached() {
+  let mut tester = setup();
+
+  assert_eq!(tester.propose(0, 1, rate_update(dec!(0.6))), Ok(0));
+
+  // The proposer approval is recorded with the proposal
+  let proposal = tester.get_proposal(0).unwrap();
+  assert_eq!(proposal.approvals, indexset!(tester.approver(0)));
+  assert_eq!(current_rate(&mut tester), dec!(0.5));
+
+  assert_eq!(tester.approve(0, 0), Err(error(ApprovalError::AlreadyApproved(0))));
+  assert_eq!(tester.approve(3, 0), Err(error(ApprovalError::NotAnApprover(tester.approver(3)))));
+
+  assert_eq!(tester.approve(1, 0), Ok(true));
+  assert_eq!(current_rate(&mut tester), dec!(0.6));
+  assert_eq!(tester.get_proposal(0).err(), Some(error(ApprovalError::ProposalNotFound(0))));
+}
+
+#[test]
+fn proposal_is_applied_right_away_with_a_quorum_of_one() {
+  let mut tester = setup();
+
+  tester.update_approvers(indexset!(tester.approver(0)), 1).unwrap();
+
+  assert_eq!(tester.propose(0, 1, rate_update(dec!(0.6))), Ok(0));
+  assert_eq!(current_rate(&mut tester), dec!(0.6));
+  assert_eq!(tester.get_proposal(0).err(), Some(error(ApprovalError::ProposalNotFound(0))));
+}
+
+#[test]
+fn approvals_of_removed_approvers_do_not_count() {
+  let mut tester = setup();
+
+  tester.propose(0, 1, rate_update(dec!(0.6))).unwrap();
+
+  assert_eq!(
+    tester.update_approvers(indexset!(tester.approver(1), tester.approver(2)), 3),
+    Err(error(ApprovalError::InvalidQuorum(3)))
+  );
+  tester.update_approvers(indexset!(tester.approver(1), tester.approver(2)), 2).unwrap();
+
+  // The proposer is not an approver anymore, its approval is ignored
+  assert_eq!(tester.approve(1, 0), Ok(false));
+  assert_eq!(current_rate(&mut tester), dec!(0.5));
+
+  assert_eq!(tester.approve(2, 0), Ok(true));
+  assert_eq!(current_rate(&mut tester), dec!(0.6));
+}
+
+#[test]
+fn proposal_reaching_a_lowered_quorum_is_executed() {
+  let mut tester = setup();
+
+  tester.propose(0, 1, rate_update(dec!(0.6))).unwrap();
+
+  // Not enough approvals yet, the proposal is kept
+  assert_eq!(tester.execute_proposal(0), Ok(false));
+  assert_eq!(current_rate(&mut tester), dec!(0.5));
+
+  tester.update_approvers(indexset!(tester.approver(0), tester.approver(1)), 1).unwrap();
+
+  // The proposer cannot approve again, the proposal is executed with its recorded approval
+  assert_eq!(tester.approve(0, 0), Err(error(ApprovalError::AlreadyApproved(0))));
+  assert_eq!(tester.execute_proposal(0), Ok(true));
+  assert_eq!(current_rate(&mut tester), dec!(0.6));
+  assert_eq!(tester.execute_proposal(0), Err(error(ApprovalError::ProposalNotFound(0))));
+}
+
+#[test]
+fn expired_proposal_cannot_be_approved() {
+  let mut tester = setup();
+
+  tester.propose(0, 1, rate_update(dec!(0.6))).unwrap();
+
+  tester.set_time(HOUR);
+
+  assert_eq!(tester.approve(1, 0), Err(error(ApprovalError::ProposalExpired(0))));
+  assert_eq!(tester.execute_proposal(0), Err(error(ApprovalError::ProposalExpired(0))));
+  assert_eq!(current_rate(&mut tester), dec!(0.5));
+
+  // Anyone can clean up an expired proposal
+  tester.cancel_proposal(2, 0).unwrap();
+  assert_eq!(tester.get_proposal(0).err(), Some(error(ApprovalError::ProposalNotFound(0))));
+}
+
+#[test]
+fn only_the_proposer_cancels_a_live_proposal() {
+  let mut tester = setup();
+
+  tester.propose(0, 1, rate_update(dec!(0.6))).unwrap();
+
+  assert_eq!(tester.cancel_proposal(1, 0), Err(error(ApprovalError::NotTheProposer(0))));
+
+  tester.cancel_proposal(0, 0).unwrap();
+  assert_eq!(tester.approve(1, 0), Err(error(ApprovalError::ProposalNotFound(0))));
+}
+
+#[test]
+fn rejected_update_keeps_the_proposal() {
+  let mut tester = setup();
+
+  assert_eq!(
+    tester.propose(0, 1, rate_update(dec!(1.5))),
+    Err(error(ApprovalError::Config(ConfigError::ValidationFailed(
+      "Invalid TesterConfig::rate".to_string()
+    ))))
+  );
+
+  tester.propose(0, 1, rate_update(dec!(0.6))).unwrap();
+  tester.remove_entry(1).unwrap();
+
+  // The update fails at quorum, the proposal and its approvals are left untouched
+  assert_eq!(tester.approve(1, 0), Err(error(ApprovalError::Config(ConfigError::KeyNotFound(1)))));
+
+  let proposal = tester.get_proposal(0).unwrap();
+  assert_eq!(proposal.approvals, indexset!(tester.approver(0)));
+}
@@ -0,0 +1,454 @@
+mod harness;
+
+use common::prelude::{ConfigError, HistoryRetentionPolicy};
+use harness::{at, Tester, HOUR};
+use scrypto_test::prelude::*;
+use test_helpers::blueprints::config_manager_tester::{
+  ConfigEntryCreated, ConfigEntryRemoved, ConfigEntryUpdated, ConfigVersionExpired, TesterConfig, TesterConfigInputs, UpdateTesterConfigInput,
+};
+
+fn setup(default_expiration_time: Option<u64>, history_retention: HistoryRetentionPolicy) -> Tester {
+  Tester::new(default_expiration_time, history_retention, &[0], 1, None)
+}
+
+fn config(rate: Decimal) -> TesterConfig {
+  TesterConfig { rate, max_items: 10 }
+}
+
+fn rate_update(rate: Decimal) -> TesterConfigInputs {
+  indexset!(UpdateTesterConfigInput::Rate(rate))
+}
+
+fn error(err: ConfigError<u16>) -> String {
+  err.to_string()
+}
+
+#[test]
+fn pending_update_is_executed_once_active() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+
+  assert_eq!(tester.propose_update(1, rate_update(dec!(0.6)), HOUR), Ok(at(HOUR)));
+  assert_eq!(
+    tester.propose_update(1, rate_update(dec!(0.7)), HOUR),
+    Err(error(ConfigError::PendingUpdateAlreadyExists(1)))
+  );
+
+  // The timelock has not elapsed yet
+  assert_eq!(tester.execute_pending(1), Err(error(ConfigError::PendingUpdateNotActive(1))));
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.5))));
+
+  tester.set_time(HOUR);
+
+  tester.execute_pending(1).unwrap();
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.6))));
+  assert_eq!(tester.execute_pending(1), Err(error(ConfigError::PendingUpdateNotFound(1))));
+}
+
+#[test]
+fn durations_overflowing_i64_are_rejected() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+
+  assert_eq!(
+    tester.propose_update(1, rate_update(dec!(0.6)), u64::MAX),
+    Err(error(ConfigError::InvalidDuration(u64::MAX)))
+  );
+  assert_eq!(
+    tester.update_entry_with_expiration(1, rate_update(dec!(0.6)), Some(i64::MAX as u64 + 1)),
+    Err(error(ConfigError::InvalidDuration(i64::MAX as u64 + 1)))
+  );
+}
+
+#[test]
+fn duplicate_key_is_rejected() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+
+  assert_eq!(tester.new_entry(1, config(dec!(0.6))), Err(error(ConfigError::AlreadyExists(1))));
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.5))));
+}
+
+#[test]
+fn rollback_restores_a_version_of_the_same_key() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+  tester.new_entry(2, config(dec!(0.7))).unwrap();
+
+  tester.rollback_to_version(1, 0).unwrap();
+
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.5))));
+  assert_eq!(tester.get_current_version(1), Ok(3));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0, 1, 3]);
+
+  assert_eq!(tester.rollback_to_version(1, 2), Err(error(ConfigError::VersionKeyMismatch(2))));
+  assert_eq!(tester.rollback_to_version(1, 10), Err(error(ConfigError::VersionNotFound(10))));
+}
+
+#[test]
+fn only_expired_versions_are_pruned() {
+  let mut tester = setup(Some(HOUR), HistoryRetentionPolicy::KeepLast(1));
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+
+  // The previous version is still in its grace period
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0, 1]);
+  assert_eq!(tester.get_history_entry(0), Ok((config(dec!(0.5)), true)));
+
+  tester.set_time(HOUR);
+
+  tester.update_entry(1, rate_update(dec!(0.7))).unwrap();
+
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![2]);
+  assert_eq!(tester.get_history_entry(0), Err(error(ConfigError::VersionPruned(0))));
+  assert_eq!(tester.get_history_entry(3), Err(error(ConfigError::VersionNotFound(3))));
+}
+
+#[test]
+fn retention_requires_a_default_expiration() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+
+  assert_eq!(
+    tester.update_history_retention(HistoryRetentionPolicy::KeepLast(1)),
+    Err(error(ConfigError::RetentionWithoutExpiration))
+  );
+
+  tester.update_default_expiration(Some(HOUR)).unwrap();
+  tester.update_history_retention(HistoryRetentionPolicy::KeepLast(1)).unwrap();
+
+  assert_eq!(
+    tester.update_default_expiration(None),
+    Err(error(ConfigError::RetentionWithoutExpiration))
+  );
+
+  tester.update_entry(1, rate_update(dec!(0.7))).unwrap();
+
+  tester.set_time(2 * HOUR);
+
+  // Versions recorded without expiration are never pruned
+  assert_eq!(tester.prune_history(1, 10), Ok(0));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0, 1, 2]);
+}
+
+#[test]
+fn expired_versions_are_pruned_around_versions_without_expiration() {
+  let mut tester = setup(Some(HOUR), HistoryRetentionPolicy::KeepLast(1));
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.update_entry_with_expiration(1, rate_update(dec!(0.6)), None).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.7))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.8))).unwrap();
+
+  tester.set_time(HOUR);
+
+  // The version without expiration is skipped over instead of stopping the pruning
+  assert_eq!(tester.prune_history(1, 10), Ok(2));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![1, 3]);
+  assert_eq!(tester.get_history_entry(2), Err(error(ConfigError::VersionPruned(2))));
+  assert_eq!(tester.get_history_entry(1), Ok((config(dec!(0.6)), true)));
+
+  tester.update_entry(1, rate_update(dec!(0.9))).unwrap();
+
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![1, 4]);
+  assert_eq!(
+    tester.get_latest_n_history(1, 10),
+    Ok(vec![(4, config(dec!(0.9))), (1, config(dec!(0.6)))])
+  );
+}
+
+#[test]
+fn removed_entry_leaves_a_tombstone() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.propose_update(1, rate_update(dec!(0.6)), HOUR).unwrap();
+
+  tester.remove_entry(1).unwrap();
+
+  assert_eq!(tester.get_current_entry(1), Err(error(ConfigError::KeyNotFound(1))));
+  assert_eq!(tester.get_history_entry(1), Err(error(ConfigError::Removed(1))));
+  assert_eq!(tester.remove_entry(1), Err(error(ConfigError::KeyNotFound(1))));
+
+  // The pending update was dropped with the entry
+  assert_eq!(tester.execute_pending(1), Err(error(ConfigError::PendingUpdateNotFound(1))));
+
+  tester.new_entry(1, config(dec!(0.7))).unwrap();
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0, 1, 2]);
+}
+
+#[test]
+fn latest_history_skips_tombstones() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.new_entry(2, config(dec!(0.1))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+  tester.remove_entry(1).unwrap();
+  tester.new_entry(1, config(dec!(0.7))).unwrap();
+
+  assert_eq!(tester.get_latest_n_history(1, 2), Ok(vec![(4, config(dec!(0.7)))]));
+  assert_eq!(
+    tester.get_latest_n_history(1, 10),
+    Ok(vec![(4, config(dec!(0.7))), (2, config(dec!(0.6))), (0, config(dec!(0.5)))])
+  );
+  assert_eq!(tester.get_latest_n_history(3, 10), Ok(vec![]));
+}
+
+#[test]
+fn scheduled_entry_is_read_once_due_and_versioned_once_applied() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+
+  assert_eq!(
+    tester.schedule_entry(1, config(dec!(0.7)), at(0)),
+    Err(error(ConfigError::ScheduledEntryNotInFuture(1)))
+  );
+
+  tester.schedule_entry(1, config(dec!(0.7)), at(HOUR)).unwrap();
+
+  assert_eq!(
+    tester.schedule_entry(1, config(dec!(0.8)), at(HOUR)),
+    Err(error(ConfigError::ScheduledEntryAlreadyExists(1)))
+  );
+
+  // The version of the scheduled entry is recorded right away
+  assert_eq!(tester.get_history_entry(1), Ok((config(dec!(0.7)), true)));
+
+  // An update before the effective time keeps the schedule
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.6))));
+  assert_eq!(tester.get_current_version(1), Ok(2));
+
+  tester.set_time(HOUR);
+
+  // The due entry is returned with its version before it is applied
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.7))));
+  assert_eq!(tester.get_current_version(1), Ok(1));
+  assert_eq!(tester.get_all_current_entries(0, 10), Ok(vec![(1, 1, config(dec!(0.7)))]));
+
+  assert_eq!(tester.apply_scheduled_entry(1), Ok(true));
+  assert_eq!(tester.apply_scheduled_entry(1), Ok(false));
+  assert_eq!(tester.get_current_version(1), Ok(1));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0, 1, 2]);
+}
+
+#[test]
+fn scheduled_entry_only_replaces_an_existing_key() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  assert_eq!(
+    tester.schedule_entry(1, config(dec!(0.5)), at(HOUR)),
+    Err(error(ConfigError::KeyNotFound(1)))
+  );
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.schedule_entry(1, config(dec!(0.7)), at(HOUR)).unwrap();
+
+  // A schedule which is not due yet is dropped with its version
+  tester.cancel_scheduled_entry(1).unwrap();
+  assert_eq!(tester.cancel_scheduled_entry(1), Err(error(ConfigError::ScheduledEntryNotFound(1))));
+  assert_eq!(tester.get_history_entry(1), Err(error(ConfigError::VersionPruned(1))));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0]);
+
+  tester.schedule_entry(1, config(dec!(0.7)), at(2 * HOUR)).unwrap();
+
+  tester.set_time(2 * HOUR);
+
+  // The due entry has already been read as the current entry, cancelling applies it instead
+  assert_eq!(tester.cancel_scheduled_entry(1), Err(error(ConfigError::ScheduledEntryNotFound(1))));
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.7))));
+  assert_eq!(tester.get_current_version(1), Ok(2));
+
+  assert_eq!(tester.get_entry_count(), 1);
+  assert_eq!(tester.get_all_current_entries(0, 10), Ok(vec![(1, 2, config(dec!(0.7)))]));
+}
+
+#[test]
+fn version_of_a_due_scheduled_entry_is_not_pruned() {
+  let mut tester = setup(Some(HOUR), HistoryRetentionPolicy::KeepLast(1));
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.schedule_entry(1, config(dec!(0.7)), at(HOUR)).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+
+  tester.set_time(3 * HOUR);
+
+  // The scheduled version is the current one although a newer version was recorded
+  assert_eq!(tester.prune_history(1, 10), Ok(1));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![1, 2]);
+  assert_eq!(tester.get_current_version(1), Ok(1));
+}
+
+#[test]
+fn batch_with_an_invalid_update_applies_nothing() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.new_entry(2, config(dec!(0.5))).unwrap();
+
+  assert_eq!(
+    tester.update_entries(vec![(1, rate_update(dec!(0.6))), (2, rate_update(dec!(1.5)))]),
+    Err(error(ConfigError::ValidationFailed("Invalid TesterConfig::rate".to_string())))
+  );
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.5))));
+  assert_eq!(tester.get_current_version(1), Ok(0));
+
+  // An empty batch does not take a changeset id
+  assert_eq!(tester.update_entries(vec![]), Err(error(ConfigError::EmptyBatch)));
+
+  // A key updated twice in a batch is updated from its staged entry
+  let updates = vec![
+    (1, rate_update(dec!(0.6))),
+    (2, rate_update(dec!(0.7))),
+    (1, indexset!(UpdateTesterConfigInput::MaxItems(20))),
+  ];
+  assert_eq!(tester.update_entries(updates), Ok(0));
+  assert_eq!(
+    tester.get_current_entry(1),
+    Ok(TesterConfig {
+      rate: dec!(0.6),
+      max_items: 20,
+    })
+  );
+  assert_eq!(tester.get_current_entry(2), Ok(config(dec!(0.7))));
+
+  // Each update of the batch is recorded under the changeset
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), vec![0, 2, 4]);
+  for version in [2, 3, 4] {
+    assert_eq!(tester.get_history_changeset(version), Ok(Some(0)));
+  }
+  assert_eq!(tester.get_history_changeset(0), Ok(None));
+}
+
+#[test]
+fn key_registry_keeps_positions_contiguous() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  for key in 1..=3 {
+    tester.new_entry(key, config(dec!(0.5))).unwrap();
+  }
+
+  assert_eq!(
+    tester.get_all_current_entries(0, 10),
+    Ok(vec![(1, 0, config(dec!(0.5))), (2, 1, config(dec!(0.5))), (3, 2, config(dec!(0.5))),])
+  );
+
+  // The last key takes the position of the removed one
+  tester.remove_entry(1).unwrap();
+
+  assert_eq!(
+    tester.get_all_current_entries(0, 10),
+    Ok(vec![(3, 2, config(dec!(0.5))), (2, 1, config(dec!(0.5)))])
+  );
+  assert_eq!(tester.get_all_current_entries(1, 10), Ok(vec![(2, 1, config(dec!(0.5)))]));
+}
+
+#[test]
+fn diff_versions_requires_the_same_key() {
+  let mut tester = setup(None, HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+  tester.new_entry(2, config(dec!(0.7))).unwrap();
+
+  assert_eq!(tester.diff_versions(0, 1), Ok(rate_update(dec!(0.6))));
+  assert_eq!(tester.diff_versions(0, 2), Err(error(ConfigError::VersionKeyMismatch(2))));
+}
+
+#[test]
+fn expiration_can_be_overridden_and_extended() {
+  let mut tester = setup(Some(HOUR), HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+
+  // The new version never expires instead of using the default expiration
+  tester.update_entry_with_expiration(1, rate_update(dec!(0.6)), None).unwrap();
+
+  tester.extend_expiration(0, HOUR).unwrap();
+  tester.extend_expiration(1, HOUR).unwrap();
+
+  tester.set_time(HOUR);
+
+  assert_eq!(tester.get_history_entry(0), Ok((config(dec!(0.5)), true)));
+
+  tester.set_time(2 * HOUR);
+
+  // An expired version resolves to the current entry and cannot be revived
+  assert_eq!(tester.get_history_entry(0), Ok((config(dec!(0.6)), false)));
+  assert_eq!(tester.extend_expiration(0, HOUR), Err(error(ConfigError::Expired(0))));
+  assert_eq!(tester.set_entry_expired(0), Err(error(ConfigError::Expired(0))));
+
+  assert_eq!(tester.get_history_entry(1), Ok((config(dec!(0.6)), true)));
+}
+
+#[test]
+fn current_entries_work_without_history() {
+  let mut tester = Tester::without_history(Some(HOUR));
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  tester.update_entry(1, rate_update(dec!(0.6))).unwrap();
+
+  assert_eq!(tester.get_current_entry(1), Ok(config(dec!(0.6))));
+  assert_eq!(tester.get_current_version(1), Ok(1));
+  assert_eq!(tester.get_entry_count(), 1);
+
+  assert_eq!(tester.get_history_entry(0), Err(error(ConfigError::HistoryDisabled)));
+  assert_eq!(tester.rollback_to_version(1, 0), Err(error(ConfigError::HistoryDisabled)));
+  assert_eq!(
+    tester.update_entry_with_expiration(1, rate_update(dec!(0.7)), None),
+    Err(error(ConfigError::HistoryDisabled))
+  );
+  assert_eq!(tester.get_all_current_entries(0, 10), Err(error(ConfigError::KeyRegistryDisabled)));
+  assert_eq!(tester.get_versions_for_key(1, 0, 10), Vec::<u64>::new());
+  assert_eq!(tester.prune_history(1, 10), Ok(0));
+
+  tester.remove_entry(1).unwrap();
+  assert_eq!(tester.get_current_entry(1), Err(error(ConfigError::KeyNotFound(1))));
+}
+
+#[test]
+fn events_are_emitted_for_each_change() {
+  let mut tester = setup(Some(HOUR), HistoryRetentionPolicy::KeepAll);
+
+  tester.new_entry(1, config(dec!(0.5))).unwrap();
+  assert_eq!(tester.event_names(), vec!["ConfigEntryCreated"]);
+  let created: ConfigEntryCreated = tester.event(0);
+  assert_eq!((created.key, created.version), (1, 0));
+
+  tester.update_entries(vec![(1, rate_update(dec!(0.6)))]).unwrap();
+  assert_eq!(tester.event_names(), vec!["ConfigEntryUpdated"]);
+  let updated: ConfigEntryUpdated = tester.event(0);
+  assert_eq!((updated.key, updated.old_version, updated.new_version), (1, 0, 1));
+  assert_eq!(updated.update_inputs, Some(rate_update(dec!(0.6))));
+  assert_eq!(updated.changeset, Some(0));
+
+  tester.rollback_to_version(1, 0).unwrap();
+  let updated: ConfigEntryUpdated = tester.event(0);
+  assert_eq!((updated.old_version, updated.new_version), (1, 2));
+  assert_eq!(updated.update_inputs, None);
+  assert_eq!(updated.changeset, None);
+
+  tester.set_entry_expired(0).unwrap();
+  assert_eq!(tester.event_names(), vec!["ConfigVersionExpired"]);
+  let expired: ConfigVersionExpired = tester.event(0);
+  assert_eq!((expired.key, expired.version), (1, 0));
+
+  tester.remove_entry(1).unwrap();
+  assert_eq!(tester.event_names(), vec!["ConfigEntryRemoved"]);
+  let removed: ConfigEntryRemoved = tester.event(0);
+  assert_eq!((removed.key, removed.version), (1, 3));
+
+  // Failed changes emit nothing
+  assert!(tester.update_entry(1, rate_update(dec!(0.7))).is_err());
+  assert!(tester.event_names().is_empty());
+}
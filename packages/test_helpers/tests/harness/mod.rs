@@ -0,0 +1,205 @@
+// Each test file only uses part of the harness
+#![allow(dead_code)]
+
+use common::prelude::{HistoryRetentionPolicy, Proposal};
+use scrypto_test::prelude::*;
+use std::sync::OnceLock;
+use test_helpers::blueprints::config_manager_tester::{TesterConfig, TesterConfigInputs};
+
+pub const START: i64 = 1_800_000_000;
+pub const HOUR: u64 = 3_600;
+
+/// The package is compiled once and published on the ledger of each test
+static PACKAGE: OnceLock<(Vec<u8>, PackageDefinition)> = OnceLock::new();
+
+/// Generates a method calling the blueprint method of the same name, the output of the blueprint
+/// method is decoded as the return type
+macro_rules! tester_methods {
+  ($($name:ident($($arg:ident: $arg_type:ty),*) -> $output:ty;)*) => {
+    $(
+      pub fn $name(&mut self, $($arg: $arg_type),*) -> $output {
+        self.call(None, stringify!($name), manifest_args!($($arg),*))
+      }
+    )*
+  };
+}
+
+/// Generates a method calling the blueprint method of the same name with the signature of the
+/// approver `signer`, passed as the first argument of the blueprint method
+macro_rules! signed_tester_methods {
+  ($($name:ident($($arg:ident: $arg_type:ty),*) -> $output:ty;)*) => {
+    $(
+      pub fn $name(&mut self, signer: usize, $($arg: $arg_type),*) -> $output {
+        let approver = self.approver(signer);
+        self.call(Some(signer), stringify!($name), manifest_args!(approver, $($arg),*))
+      }
+    )*
+  };
+}
+
+/// A `ConfigManagerTester` component on its own ledger, with four signers whose virtual badges
+/// can be used as approvers
+pub struct Tester {
+  ledger: DefaultLedgerSimulator,
+  component: ComponentAddress,
+  signers: Vec<Secp256k1PublicKey>,
+  events: Vec<(String, Vec<u8>)>,
+}
+
+impl Tester {
+  /// Instantiates a tester at `START` with the signers `approvers` as approvers
+  pub fn new(
+    default_expiration_time: Option<u64>,
+    history_retention: HistoryRetentionPolicy,
+    approvers: &[usize],
+    quorum: u8,
+    proposal_expiration_time: Option<u64>,
+  ) -> Self {
+    Self::instantiate(
+      default_expiration_time,
+      Some(history_retention),
+      approvers,
+      quorum,
+      proposal_expiration_time,
+    )
+  }
+
+  /// Instantiates a tester at `START` which neither records the history nor tracks the keys, the
+  /// signer 0 is the only approver
+  pub fn without_history(default_expiration_time: Option<u64>) -> Self {
+    Self::instantiate(default_expiration_time, None, &[0], 1, None)
+  }
+
+  fn instantiate(
+    default_expiration_time: Option<u64>,
+    history_retention: Option<HistoryRetentionPolicy>,
+    approvers: &[usize],
+    quorum: u8,
+    proposal_expiration_time: Option<u64>,
+  ) -> Self {
+    // A validator is needed to move the clock to another round
+    let genesis = CustomGenesis::default(Epoch::of(1), CustomGenesis::default_consensus_manager_config());
+    let mut ledger = LedgerSimulatorBuilder::new().with_custom_genesis(genesis).without_kernel_trace().build();
+
+    let package = PACKAGE.get_or_init(|| Compile::compile(this_package!(), CompileProfile::Fast));
+    let package_address = ledger.publish_package(package.clone(), BTreeMap::new(), OwnerRole::None);
+
+    let signers: Vec<_> = (0..4).map(|_| ledger.new_key_pair().0).collect();
+    let approvers: IndexSet<_> = approvers
+      .iter()
+      .map(|signer| NonFungibleGlobalId::from_public_key(&signers[*signer]))
+      .collect();
+
+    let manifest = ManifestBuilder::new()
+      .lock_fee_from_faucet()
+      .call_function(
+        package_address,
+        "ConfigManagerTester",
+        "instantiate",
+        manifest_args!(default_expiration_time, history_retention, approvers, quorum, proposal_expiration_time),
+      )
+      .build();
+    let component = ledger
+      .execute_manifest(manifest, vec![])
+      .expect_commit_success()
+      .new_component_addresses()[0];
+
+    let mut tester = Self {
+      ledger,
+      component,
+      signers,
+      events: Vec::new(),
+    };
+    tester.set_time(0);
+
+    tester
+  }
+
+  /// The virtual badge of the signer
+  pub fn approver(&self, signer: usize) -> NonFungibleGlobalId {
+    NonFungibleGlobalId::from_public_key(&self.signers[signer])
+  }
+
+  /// Moves the ledger clock to `seconds` after `START`, the time can only move forward
+  pub fn set_time(&mut self, seconds: u64) {
+    let round = Round::of(self.ledger.get_consensus_manager_state().round.number() + 1);
+
+    self
+      .ledger
+      .advance_to_round_at_timestamp(round, at(seconds).seconds_since_unix_epoch * 1000)
+      .expect_commit_success();
+  }
+
+  fn call<O: ScryptoDecode>(&mut self, signer: Option<usize>, method: &str, args: ManifestArgs) -> O {
+    let manifest = ManifestBuilder::new()
+      .lock_fee_from_faucet()
+      .call_method(self.component, method, args)
+      .build();
+    let proofs: Vec<_> = signer.map(|signer| self.approver(signer)).into_iter().collect();
+
+    let receipt = self.ledger.execute_manifest(manifest, proofs);
+    let commit = receipt.expect_commit_success();
+
+    self.events = commit
+      .application_events
+      .iter()
+      .filter(|(EventTypeIdentifier(emitter, _), _)| {
+        matches!(emitter, Emitter::Method(node_id, ModuleId::Main) if *node_id == *self.component.as_node_id())
+      })
+      .map(|(EventTypeIdentifier(_, name), data)| (name.clone(), data.clone()))
+      .collect();
+
+    commit.output(1)
+  }
+
+  /// The names of the events emitted by the component during the last call
+  pub fn event_names(&self) -> Vec<&str> {
+    self.events.iter().map(|(name, _)| name.as_str()).collect()
+  }
+
+  /// Decodes the event emitted at `index` during the last call
+  pub fn event<T: ScryptoDecode>(&self, index: usize) -> T {
+    scrypto_decode(&self.events[index].1).unwrap()
+  }
+
+  tester_methods! {
+    update_default_expiration(new_default_expiration: Option<u64>) -> Result<(), String>;
+    update_history_retention(new_history_retention: HistoryRetentionPolicy) -> Result<(), String>;
+    new_entry(key: u16, entry: TesterConfig) -> Result<(), String>;
+    remove_entry(key: u16) -> Result<(), String>;
+    update_entry(key: u16, update_inputs: TesterConfigInputs) -> Result<(), String>;
+    update_entry_with_expiration(key: u16, update_inputs: TesterConfigInputs, expiration_time: Option<u64>) -> Result<(), String>;
+    update_entries(updates: Vec<(u16, TesterConfigInputs)>) -> Result<u64, String>;
+    propose_update(key: u16, update_inputs: TesterConfigInputs, delay_seconds: u64) -> Result<Instant, String>;
+    execute_pending(key: u16) -> Result<(), String>;
+    rollback_to_version(key: u16, version: u64) -> Result<(), String>;
+    schedule_entry(key: u16, entry: TesterConfig, effective_at: Instant) -> Result<(), String>;
+    cancel_scheduled_entry(key: u16) -> Result<(), String>;
+    apply_scheduled_entry(key: u16) -> Result<bool, String>;
+    extend_expiration(version: u64, seconds: u64) -> Result<(), String>;
+    set_entry_expired(version: u64) -> Result<(), String>;
+    prune_history(key: u16, max_items: u64) -> Result<u64, String>;
+    get_entry_count() -> u16;
+    get_current_entry(key: u16) -> Result<TesterConfig, String>;
+    get_current_version(key: u16) -> Result<u64, String>;
+    get_history_entry(version: u64) -> Result<(TesterConfig, bool), String>;
+    get_history_changeset(version: u64) -> Result<Option<u64>, String>;
+    get_versions_for_key(key: u16, offset: u64, limit: u64) -> Vec<u64>;
+    get_latest_n_history(key: u16, n: u64) -> Result<Vec<(u64, TesterConfig)>, String>;
+    get_all_current_entries(offset: u64, limit: u64) -> Result<Vec<(u16, u64, TesterConfig)>, String>;
+    diff_versions(from_version: u64, to_version: u64) -> Result<TesterConfigInputs, String>;
+    update_approvers(approvers: IndexSet<NonFungibleGlobalId>, quorum: u8) -> Result<(), String>;
+    execute_proposal(proposal_id: u64) -> Result<bool, String>;
+    get_proposal(proposal_id: u64) -> Result<Proposal<u16, TesterConfigInputs>, String>;
+  }
+
+  signed_tester_methods! {
+    propose(key: u16, update_inputs: TesterConfigInputs) -> Result<u64, String>;
+    approve(proposal_id: u64) -> Result<bool, String>;
+    cancel_proposal(proposal_id: u64) -> Result<(), String>;
+  }
+}
+
+pub fn at(seconds: u64) -> Instant {
+  Instant::new(START + seconds as i64)
+}